@@ -0,0 +1,272 @@
+//! Barnes-Hut approximation for summing fields over many charges
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+const MAX_DEPTH: usize = 32;
+
+/// How fields from many charges are summed
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Solver {
+    /// Opening angle, 0 always sums every charge exactly
+    pub theta: f32,
+    /// Below this many charges the tree isn't worth building
+    pub exact_below: usize,
+    /// 0 uses every core
+    pub threads: usize,
+}
+
+/// A charge as seen by the field, qv is what the magnetic field depends on
+#[derive(Copy, Clone)]
+pub struct Source {
+    /// Charge
+    pub q: f32,
+    /// Position
+    pub p: Vec2,
+    /// Charge times velocity
+    pub qv: Vec2,
+}
+
+struct Node {
+    center: Vec2,
+    half_size: f32,
+    q: f32,
+    centroid: Vec2,
+    qv: Vec2,
+    children: Vec<usize>,
+    sources: Vec<usize>,
+}
+impl Node {
+    fn contains(&self, p: Vec2) -> bool {
+        let d = (p - self.center).abs();
+        return d.x <= self.half_size && d.y <= self.half_size;
+    }
+
+    // Whether image shifts every point of the cell by the same amount. When periodic images
+    // wrap part of the cell to the other side of the box, its sources have to be summed
+    // separately instead of through the centroid
+    fn moves_as_one<I: Fn(Vec2) -> Vec2>(&self, shift: Vec2, image: &I) -> bool {
+        // Shifts only ever differ by a whole box, which is far more than half a cell
+        return [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .into_iter()
+            .map(|(x, y)| self.center + Vec2::new(x, y) * self.half_size)
+            .all(|corner| (image(corner) - corner - shift).abs().max_element() <= self.half_size);
+    }
+}
+
+/// Barnes-Hut tree over the sources, or a flat list when the solver asks for exact sums
+pub struct QuadTree {
+    theta: f32,
+    sources: Vec<Source>,
+    nodes: Vec<Node>,
+}
+impl QuadTree {
+    /// Builds the tree over sources, opening nodes according to solver.theta
+    pub fn new(sources: Vec<Source>, solver: Solver) -> Self {
+        let mut tree = Self {
+            theta: solver.theta,
+            sources,
+            nodes: vec![],
+        };
+        if tree.sources.is_empty() || tree.sources.len() < solver.exact_below || tree.theta <= 0.0 {
+            return tree;
+        }
+
+        let mut min = Vec2::splat(f32::INFINITY);
+        let mut max = Vec2::splat(f32::NEG_INFINITY);
+        for s in tree.sources.iter() {
+            min = min.min(s.p);
+            max = max.max(s.p);
+        }
+        let center = (min + max) / 2.0;
+        let half_size = (max - min).max_element() / 2.0 + f32::EPSILON;
+
+        let indices = (0..tree.sources.len()).collect();
+        tree.build(indices, center, half_size, 0);
+        return tree;
+    }
+
+    fn build(&mut self, indices: Vec<usize>, center: Vec2, half_size: f32, depth: usize) -> usize {
+        let mut q = 0.0;
+        let mut abs_q = 0.0;
+        let mut weighted = Vec2::ZERO;
+        let mut mean = Vec2::ZERO;
+        let mut qv = Vec2::ZERO;
+        for i in indices.iter() {
+            let s = &self.sources[*i];
+            q += s.q;
+            abs_q += s.q.abs();
+            weighted += s.p * s.q.abs();
+            mean += s.p;
+            qv += s.qv;
+        }
+        // Centre of charge magnitude so opposite charges don't cancel out the position
+        let centroid = if abs_q > 0.0 {
+            weighted / abs_q
+        } else {
+            mean / indices.len() as f32
+        };
+
+        let node_i = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            half_size,
+            q,
+            centroid,
+            qv,
+            children: vec![],
+            sources: vec![],
+        });
+
+        if indices.len() <= 1 || depth >= MAX_DEPTH {
+            self.nodes[node_i].sources = indices;
+            return node_i;
+        }
+
+        let mut quadrants: [Vec<usize>; 4] = [vec![], vec![], vec![], vec![]];
+        for i in indices {
+            let p = self.sources[i].p;
+            let quadrant = (p.x >= center.x) as usize + 2 * (p.y >= center.y) as usize;
+            quadrants[quadrant].push(i);
+        }
+
+        let quarter = half_size / 2.0;
+        let mut children = Vec::with_capacity(4);
+        for (quadrant, quadrant_indices) in quadrants.into_iter().enumerate() {
+            if quadrant_indices.is_empty() {
+                continue;
+            }
+            let offset = Vec2::new(
+                if quadrant & 1 == 1 { quarter } else { -quarter },
+                if quadrant & 2 == 2 { quarter } else { -quarter },
+            );
+            children.push(self.build(quadrant_indices, center + offset, quarter, depth + 1));
+        }
+        self.nodes[node_i].children = children;
+
+        return node_i;
+    }
+
+    /// Calls f with (q, p, qv) for every source or group of sources acting on at,
+    /// image maps a source position to the copy of it that should be used
+    pub fn visit<I, F>(&self, at: Vec2, exclude: Option<usize>, image: I, mut f: F)
+    where
+        I: Fn(Vec2) -> Vec2,
+        F: FnMut(f32, Vec2, Vec2),
+    {
+        if self.nodes.is_empty() {
+            for (i, s) in self.sources.iter().enumerate() {
+                if Some(i) != exclude {
+                    f(s.q, image(s.p), s.qv);
+                }
+            }
+            return;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_i) = stack.pop() {
+            let node = &self.nodes[node_i];
+
+            if node.children.is_empty() {
+                for i in node.sources.iter() {
+                    if Some(*i) != exclude {
+                        let s = &self.sources[*i];
+                        f(s.q, image(s.p), s.qv);
+                    }
+                }
+                continue;
+            }
+
+            let centroid = image(node.centroid);
+            let size = node.half_size * 2.0;
+            let far = !node.contains(at) && size < self.theta * at.distance(centroid);
+            if far && node.moves_as_one(centroid - node.centroid, &image) {
+                f(node.q, centroid, node.qv);
+            } else {
+                stack.extend(node.children.iter());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Boundary;
+
+    const BOUNDS: [f32; 4] = [-50.0, 50.0, -50.0, 50.0];
+
+    // Positive charges scattered over the bounds by a fixed LCG, so every run sees the same set
+    fn scattered(n: usize) -> Vec<Source> {
+        let mut seed = 12345u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            return (seed >> 8) as f32 / (1 << 24) as f32;
+        };
+        return (0..n)
+            .map(|_| Source {
+                q: 0.5 + next(),
+                p: Vec2::new(next() * 100.0 - 50.0, next() * 100.0 - 50.0),
+                qv: Vec2::ZERO,
+            })
+            .collect();
+    }
+
+    // Coulomb field at at, along with the sum of its terms' magnitudes to measure errors against
+    fn field<I: Fn(Vec2) -> Vec2>(tree: &QuadTree, at: Vec2, image: I) -> (Vec2, f32) {
+        let (mut e, mut scale) = (Vec2::ZERO, 0.0);
+        tree.visit(at, None, image, |q, p, _| {
+            let d = at - p;
+            e += q * d / d.length().powi(3);
+            scale += q.abs() / d.length_squared();
+        });
+        return (e, scale);
+    }
+
+    fn solver(theta: f32) -> Solver {
+        return Solver {
+            theta,
+            exact_below: 0,
+            threads: 1,
+        };
+    }
+
+    #[test]
+    fn approximation_stays_close_to_exact_sum() {
+        let sources = scattered(500);
+        let exact = QuadTree::new(sources.clone(), solver(0.0));
+        let tree = QuadTree::new(sources, solver(0.5));
+
+        for at in [
+            Vec2::new(0.3, 0.7),
+            Vec2::new(-42.0, 17.5),
+            Vec2::new(70.0, -20.0),
+        ] {
+            let (expected, scale) = field(&exact, at, |p| p);
+            let (approximated, _) = field(&tree, at, |p| p);
+            assert!(approximated.distance(expected) < 0.02 * scale);
+        }
+    }
+
+    #[test]
+    fn periodic_images_match_exact_sum() {
+        // A tight row of charges around x = -26, the far side of the box as seen from at, so
+        // half of their nearest images are to the left of at and half to the right
+        let at = Vec2::new(24.0, 0.0);
+        let mut sources: Vec<Source> = (0..16)
+            .map(|i| Source {
+                q: 1.0,
+                p: Vec2::new(-27.0 + i as f32 * 0.125, 0.3),
+                qv: Vec2::ZERO,
+            })
+            .collect();
+        sources.extend(scattered(50));
+        let exact = QuadTree::new(sources.clone(), solver(0.0));
+        let tree = QuadTree::new(sources, solver(0.5));
+
+        let image = |p| at + Boundary::Periodic.displacement(at, p, BOUNDS);
+        let (expected, scale) = field(&exact, at, image);
+        let (approximated, _) = field(&tree, at, image);
+        assert!(approximated.distance(expected) < 0.02 * scale);
+    }
+}
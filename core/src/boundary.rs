@@ -0,0 +1,149 @@
+//! Edges of the simulated region
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::charge::Charge;
+
+/// What happens to charges at the edge of the bounds
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub enum Boundary {
+    /// Charges bounce off the edges, reversing the velocity across them. Pinned charges stay
+    /// where they are
+    #[default]
+    Reflecting,
+    /// Charges leaving one edge come back in at the opposite one, and forces use the
+    /// nearest image of each charge
+    Periodic,
+    /// Charges that leave the bounds are removed, unless they're pinned
+    Absorbing,
+    /// Nothing happens at the edges, charges can move off indefinitely
+    Open,
+}
+impl Boundary {
+    /// Shortest vector from a to b, using the nearest periodic image of b when wrapping
+    pub fn displacement(&self, a: Vec2, b: Vec2, bounds: [f32; 4]) -> Vec2 {
+        let mut dir = b - a;
+        if !matches!(self, Boundary::Periodic) {
+            return dir;
+        }
+
+        let [min_x, max_x, min_y, max_y] = bounds;
+        let (width, height) = (max_x - min_x, max_y - min_y);
+        dir.x -= width * (dir.x / width).round();
+        dir.y -= height * (dir.y / height).round();
+        return dir;
+    }
+
+    /// Moves, wraps or removes charges that have left the bounds
+    pub fn apply(&self, charges: &mut Vec<Charge>, bounds: [f32; 4]) {
+        let [min_x, max_x, min_y, max_y] = bounds;
+        match self {
+            Boundary::Reflecting => {
+                for c in charges.iter_mut() {
+                    if c.fixed {
+                        continue;
+                    }
+                    if c.p.x < min_x {
+                        c.p.x = min_x + (min_x - c.p.x);
+                        c.v.x = -c.v.x
+                    }
+                    if c.p.x > max_x {
+                        c.p.x = max_x - (c.p.x - max_x);
+                        c.v.x = -c.v.x
+                    }
+                    if c.p.y < min_y {
+                        c.p.y = min_y + (min_y - c.p.y);
+                        c.v.y = -c.v.y
+                    }
+                    if c.p.y > max_y {
+                        c.p.y = max_y - (c.p.y - max_y);
+                        c.v.y = -c.v.y
+                    }
+                }
+            }
+            Boundary::Periodic => {
+                for c in charges.iter_mut() {
+                    c.p.x = min_x + (c.p.x - min_x).rem_euclid(max_x - min_x);
+                    c.p.y = min_y + (c.p.y - min_y).rem_euclid(max_y - min_y);
+                }
+            }
+            Boundary::Absorbing => {
+                charges.retain(|c| {
+                    c.fixed
+                        || (c.p.x >= min_x && c.p.x <= max_x && c.p.y >= min_y && c.p.y <= max_y)
+                });
+            }
+            Boundary::Open => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    const BOUNDS: [f32; 4] = [-10.0, 10.0, -5.0, 5.0];
+
+    fn at(p: Vec2, v: Vec2) -> Charge {
+        return Charge::new(1.0, 1.0, p, v, 0.0);
+    }
+
+    #[test]
+    fn periodic_wraps_to_the_opposite_edge() {
+        let mut charges = vec![
+            at(vec2(11.0, -6.0), Vec2::ZERO),
+            at(vec2(-10.5, 0.0), vec2(-1.0, 0.0)),
+            at(vec2(3.0, 2.0), Vec2::ZERO),
+        ];
+        Boundary::Periodic.apply(&mut charges, BOUNDS);
+
+        let p: Vec<Vec2> = charges.iter().map(|c| c.p).collect();
+        assert!(p[0].distance(vec2(-9.0, 4.0)) < 1e-5, "{:?}", p[0]);
+        assert!(p[1].distance(vec2(9.5, 0.0)) < 1e-5, "{:?}", p[1]);
+        assert_eq!(p[2], vec2(3.0, 2.0));
+        assert_eq!(charges[1].v, vec2(-1.0, 0.0));
+    }
+
+    #[test]
+    fn reflecting_mirrors_position_and_velocity() {
+        let mut pinned = at(vec2(12.0, 0.0), Vec2::ZERO);
+        pinned.set_fixed(true);
+        let mut charges = vec![
+            at(vec2(11.0, 0.0), vec2(1.0, 1.0)),
+            at(vec2(-12.0, -6.0), vec2(-1.0, -1.0)),
+            pinned,
+        ];
+        Boundary::Reflecting.apply(&mut charges, BOUNDS);
+
+        assert_eq!(charges[0].p, vec2(9.0, 0.0));
+        assert_eq!(charges[0].v, vec2(-1.0, 1.0));
+        // Past a corner it comes back off both walls
+        assert_eq!(charges[1].p, vec2(-8.0, -4.0));
+        assert_eq!(charges[1].v, vec2(1.0, 1.0));
+        assert_eq!(charges[2].p, vec2(12.0, 0.0));
+    }
+
+    #[test]
+    fn absorbing_removes_free_charges_outside() {
+        let mut pinned = at(vec2(0.0, 8.0), Vec2::ZERO);
+        pinned.set_fixed(true);
+        let mut charges = vec![
+            at(vec2(0.0, 0.0), Vec2::ZERO),
+            at(vec2(10.5, 0.0), Vec2::ZERO),
+            pinned,
+            at(vec2(10.0, -5.0), Vec2::ZERO),
+            at(vec2(0.0, -5.5), Vec2::ZERO),
+        ];
+        for (i, c) in charges.iter_mut().enumerate() {
+            c.id = i as u32;
+        }
+        Boundary::Absorbing.apply(&mut charges, BOUNDS);
+
+        // On the edge still counts as inside
+        let ids: Vec<u32> = charges.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [0, 2, 3]);
+    }
+}
@@ -0,0 +1,425 @@
+//! Charges and the scene they live in
+
+use std::collections::HashSet;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    barnes_hut::{QuadTree, Solver, Source},
+    boundary::Boundary,
+    external::ExternalField,
+    field_grid::FieldGrid,
+    integrator::Integrator,
+    interaction::Interaction,
+    magnetic::{calc_lorentz, Magnetic},
+    parallel,
+};
+
+// Handing work to the task pool costs more than the force loop for a handful of charges
+const MIN_CHARGES_PER_THREAD: usize = 64;
+// A row is only as wide as the grid, so small grids are cheaper filled on one thread
+const MIN_ROWS_PER_THREAD: usize = 8;
+
+/// A whole scene, this is what save files hold
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct Charges {
+    /// Every charge in the scene, in no particular order
+    pub charges: Vec<Charge>,
+    /// How charges push on each other and what happens when they touch
+    #[serde(default)]
+    pub interaction: Interaction,
+    /// Applied and induced magnetic fields
+    #[serde(default)]
+    pub magnetic: Magnetic,
+    /// Applied electric fields, summed
+    #[serde(default)]
+    pub external: Vec<ExternalField>,
+    /// What happens at the edges of the grid
+    #[serde(default)]
+    pub boundary: Boundary,
+    // Never handed out again once used, so removed charges' ids aren't reused
+    #[serde(skip)]
+    next_id: u32,
+}
+impl Charges {
+    /// A scene with just charges, the default interaction and no applied fields, with ids
+    /// assigned as in [`Charges::assign_ids`]
+    pub fn new(charges: Vec<Charge>) -> Self {
+        let mut charges = Self {
+            charges,
+            interaction: Interaction::default(),
+            magnetic: Magnetic::default(),
+            external: vec![],
+            boundary: Boundary::default(),
+            next_id: 0,
+        };
+        charges.assign_ids();
+        return charges;
+    }
+
+    /// Gives every charge a unique id, keeping the ones a save already has where they're
+    /// unique. Saves from before ids existed load with every id at 0. Also drops any
+    /// velocity a save gives a pinned charge, so it isn't felt as a current or counted
+    /// in the energy
+    pub fn assign_ids(&mut self) {
+        let mut next_id = self.charges.iter().map(|c| c.id + 1).max().unwrap_or(0);
+        let mut seen = HashSet::new();
+        for c in self.charges.iter_mut() {
+            if c.fixed {
+                c.set_fixed(true);
+            }
+            if !seen.insert(c.id) {
+                c.id = next_id;
+                next_id += 1;
+            }
+        }
+        self.next_id = self.next_id.max(next_id);
+    }
+
+    /// Adds charge with an id no other charge has had and returns that id
+    pub fn add(&mut self, mut charge: Charge) -> u32 {
+        let max_id = self.charges.iter().map(|c| c.id + 1).max().unwrap_or(0);
+        let id = self.next_id.max(max_id);
+        self.next_id = id + 1;
+        charge.id = id;
+        self.charges.push(charge);
+        return id;
+    }
+
+    /// Fills E, B and the potential on every cell of grid at time t
+    pub fn apply_to_field(&self, grid: &mut FieldGrid, t: f32, solver: Solver) {
+        let [width, _] = grid.get_shape();
+        let bounds = grid.get_bounds();
+        let p: Vec<Vec2> = self.charges.iter().map(|c| c.p).collect();
+        let v: Vec<Vec2> = self.charges.iter().map(|c| c.v).collect();
+        let sources = self.sources(&p, &v, solver);
+
+        let coords = &grid.coords;
+        let mut rows: Vec<(&mut Vec<Vec2>, &mut Vec<f32>, &mut Vec<f32>)> = grid
+            .field
+            .iter_mut()
+            .zip(grid.magnetic.iter_mut())
+            .zip(grid.potential.iter_mut())
+            .map(|((field, magnetic), potential)| (field, magnetic, potential))
+            .collect();
+        parallel::for_each_chunk(
+            &mut rows,
+            solver.threads,
+            MIN_ROWS_PER_THREAD,
+            |start, chunk| {
+                for (i, (field_row, magnetic_row, potential_row)) in chunk.iter_mut().enumerate() {
+                    let y = start + i;
+                    for x in 0..width {
+                        let coord = coords[y][x];
+                        let (e, b) = self.calc_fields(&sources, coord, None, t, bounds);
+                        field_row[x] = e;
+                        magnetic_row[x] = b;
+                        potential_row[x] = self.calc_potential(&sources, coord, None, bounds);
+                    }
+                }
+            },
+        );
+    }
+
+    /// Advances every free charge by dt, fixed charges stay where they are
+    ///
+    /// Returns each charge's acceleration at the start of the step, so recording the state
+    /// before a step doesn't have to evaluate the forces again
+    pub fn step(
+        &mut self,
+        integrator: Integrator,
+        t: f32,
+        dt: f32,
+        bounds: [f32; 4],
+        solver: Solver,
+    ) -> Vec<Vec2> {
+        let (mut p, mut v) = self.state();
+        let start = integrator.step(&mut p, &mut v, dt, |p, v, offset| {
+            self.accelerations(p, v, t + offset, bounds, solver)
+        });
+
+        for (i, c) in self.charges.iter_mut().enumerate() {
+            if c.fixed {
+                continue;
+            }
+            c.p = p[i];
+            c.v = v[i];
+        }
+        return start;
+    }
+
+    /// Acceleration of each charge where it is now, the same as step would start from
+    pub fn current_accelerations(&self, t: f32, bounds: [f32; 4], solver: Solver) -> Vec<Vec2> {
+        let (p, v) = self.state();
+        return self.accelerations(&p, &v, t, bounds, solver);
+    }
+
+    // Positions and velocities to integrate from
+    fn state(&self) -> (Vec<Vec2>, Vec<Vec2>) {
+        let p = self.charges.iter().map(|c| c.p).collect();
+        let v = self.charges.iter().map(|c| c.v).collect();
+        return (p, v);
+    }
+
+    /// Lorentz acceleration of each charge if they were at p moving with v
+    pub fn accelerations(
+        &self,
+        p: &[Vec2],
+        v: &[Vec2],
+        t: f32,
+        bounds: [f32; 4],
+        solver: Solver,
+    ) -> Vec<Vec2> {
+        let sources = self.sources(p, v, solver);
+        let mut accs = vec![Vec2::ZERO; self.charges.len()];
+
+        parallel::for_each_chunk(
+            &mut accs,
+            solver.threads,
+            MIN_CHARGES_PER_THREAD,
+            |start, chunk| {
+                for (j, acc) in chunk.iter_mut().enumerate() {
+                    let i = start + j;
+                    let c = &self.charges[i];
+                    if c.fixed {
+                        continue;
+                    }
+
+                    let (e, b) = self.calc_fields(&sources, p[i], Some(i), t, bounds);
+                    *acc = c.q * (e + calc_lorentz(v[i], b)) / c.m;
+                }
+            },
+        );
+
+        return accs;
+    }
+
+    /// E anywhere in the scene, the tree is built once up front for repeated lookups
+    pub fn e_field_sampler(
+        &self,
+        t: f32,
+        bounds: [f32; 4],
+        solver: Solver,
+    ) -> impl Fn(Vec2) -> Vec2 + '_ {
+        let p: Vec<Vec2> = self.charges.iter().map(|c| c.p).collect();
+        let v: Vec<Vec2> = self.charges.iter().map(|c| c.v).collect();
+        let sources = self.sources(&p, &v, solver);
+        return move |at| self.calc_fields(&sources, at, None, t, bounds).0;
+    }
+
+    fn sources(&self, p: &[Vec2], v: &[Vec2], solver: Solver) -> QuadTree {
+        let sources = self
+            .charges
+            .iter()
+            .enumerate()
+            .map(|(i, c)| Source {
+                q: c.q,
+                p: p[i],
+                qv: c.q * v[i],
+            })
+            .collect();
+        return QuadTree::new(sources, solver);
+    }
+
+    // E and B at a point, leaving out the charge at exclude so charges don't act on themselves
+    fn calc_fields(
+        &self,
+        sources: &QuadTree,
+        at: Vec2,
+        exclude: Option<usize>,
+        t: f32,
+        bounds: [f32; 4],
+    ) -> (Vec2, f32) {
+        let mut e = self.calc_external_e(at, t);
+        let mut b = self.magnetic.calc_external_b(at);
+        let softening = self.interaction.softening;
+
+        sources.visit(
+            at,
+            exclude,
+            |p| self.nearest_image(at, p, bounds),
+            |q, p, qv| {
+                e += self.interaction.calc_e_force(q, p, 1.0, at);
+                b += self.magnetic.calc_point_b(qv, p, at, softening);
+            },
+        );
+
+        return (e, b);
+    }
+
+    // Potential from the charges alone, external fields aren't included
+    fn calc_potential(
+        &self,
+        sources: &QuadTree,
+        at: Vec2,
+        exclude: Option<usize>,
+        bounds: [f32; 4],
+    ) -> f32 {
+        let mut potential = 0.0;
+        sources.visit(
+            at,
+            exclude,
+            |p| self.nearest_image(at, p, bounds),
+            |q, p, _| {
+                potential += self.interaction.calc_potential(q, p, 1.0, at);
+            },
+        );
+        return potential;
+    }
+
+    /// Electrostatic energy of every pair of charges, summed through the solver's tree
+    pub fn potential_energy(&self, bounds: [f32; 4], solver: Solver) -> f32 {
+        let p: Vec<Vec2> = self.charges.iter().map(|c| c.p).collect();
+        let v: Vec<Vec2> = self.charges.iter().map(|c| c.v).collect();
+        let sources = self.sources(&p, &v, solver);
+
+        let mut energies = vec![0.0; self.charges.len()];
+        parallel::for_each_chunk(
+            &mut energies,
+            solver.threads,
+            MIN_CHARGES_PER_THREAD,
+            |start, chunk| {
+                for (j, energy) in chunk.iter_mut().enumerate() {
+                    let i = start + j;
+                    let c = &self.charges[i];
+                    *energy = c.q * self.calc_potential(&sources, c.p, Some(i), bounds);
+                }
+            },
+        );
+        // Every pair was counted once from each end
+        return energies.iter().sum::<f32>() / 2.0;
+    }
+
+    fn nearest_image(&self, from: Vec2, p: Vec2, bounds: [f32; 4]) -> Vec2 {
+        return from + self.boundary.displacement(from, p, bounds);
+    }
+
+    fn calc_external_e(&self, at: Vec2, t: f32) -> Vec2 {
+        let mut e = Vec2::ZERO;
+        for field in self.external.iter() {
+            e += field.calc_e(at, t);
+        }
+        return e;
+    }
+
+    /// Handles charges that have touched, according to the interaction settings
+    pub fn resolve_encounters(&mut self) {
+        self.interaction.resolve(&mut self.charges);
+    }
+
+    /// Applies the boundary mode to charges outside bounds
+    pub fn apply_boundary(&mut self, bounds: [f32; 4]) {
+        self.boundary.apply(&mut self.charges, bounds);
+    }
+}
+
+/// A point charge with charge q, mass m, position p and velocity v
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Charge {
+    /// Stays with the charge for the whole run, unlike its index in [`Charges::charges`]
+    #[serde(default)]
+    pub id: u32,
+    /// Charge, negative for negative charges
+    pub q: f32,
+    /// Mass, the acceleration is q / m times the Lorentz force per unit charge
+    pub m: f32,
+    /// Position in simulation units
+    pub p: Vec2,
+    /// Velocity in simulation units per second
+    pub v: Vec2,
+    /// Distance at which charges count as touching, see [`Interaction::encounter`]
+    #[serde(default)]
+    pub radius: f32,
+    /// Pinned charges never move but still exert forces
+    #[serde(default)]
+    pub fixed: bool,
+}
+impl Charge {
+    /// A free charge with id 0, [`Charges::add`] gives it a real one
+    pub fn new(q: f32, m: f32, p: Vec2, v: Vec2, radius: f32) -> Self {
+        return Self {
+            id: 0,
+            q,
+            m,
+            p,
+            v,
+            radius,
+            fixed: false,
+        };
+    }
+
+    /// Pins the charge in place, dropping its velocity
+    pub fn set_fixed(&mut self, fixed: bool) {
+        self.fixed = fixed;
+        if fixed {
+            self.v = Vec2::ZERO;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    const BOUNDS: [f32; 4] = [-100.0, 100.0, -100.0, 100.0];
+    const EXACT: Solver = Solver {
+        theta: 0.0,
+        exact_below: 0,
+        threads: 1,
+    };
+
+    #[test]
+    fn fixed_charges_ignore_their_velocity() {
+        // Pinned in a save without zeroing v, as set_fixed would have
+        let mut pinned = Charge::new(1.0, 1.0, Vec2::ZERO, vec2(5.0, 0.0), 0.0);
+        pinned.fixed = true;
+        let free = Charge::new(-1.0, 1.0, vec2(0.0, 2.0), Vec2::ZERO, 0.0);
+
+        for integrator in [Integrator::VelocityVerlet, Integrator::RK4] {
+            let mut charges = Charges::new(vec![pinned.clone(), free.clone()]);
+            assert_eq!(charges.charges[0].v, Vec2::ZERO);
+            charges.step(integrator, 0.0, 0.1, BOUNDS, EXACT);
+            assert_eq!(charges.charges[0].p, Vec2::ZERO);
+            // Pulled straight down, a moving pin would have dragged it sideways
+            assert!(charges.charges[1].p.x.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn potential_energy_sums_every_pair_once() {
+        let charges = Charges::new(vec![
+            Charge::new(1.0, 1.0, vec2(0.0, 0.0), Vec2::ZERO, 0.0),
+            Charge::new(-2.0, 1.0, vec2(3.0, 0.0), Vec2::ZERO, 0.0),
+            Charge::new(0.5, 1.0, vec2(0.0, 4.0), Vec2::ZERO, 0.0),
+        ]);
+        let interaction = &charges.interaction;
+        let mut pairs = 0.0;
+        for (i, a) in charges.charges.iter().enumerate() {
+            for b in charges.charges[i + 1..].iter() {
+                pairs += interaction.calc_potential(a.q, a.p, b.q, b.p);
+            }
+        }
+        assert!((charges.potential_energy(BOUNDS, EXACT) - pairs).abs() < 1e-5 * pairs.abs());
+    }
+
+    #[test]
+    fn ids_stay_unique_and_are_not_reused() {
+        // As loaded from a save without ids
+        let charge = Charge::new(1.0, 1.0, Vec2::ZERO, Vec2::ZERO, 0.0);
+        let mut charges = Charges::new(vec![charge.clone(), charge.clone(), charge.clone()]);
+        let ids: Vec<u32> = charges.charges.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+
+        charges.charges.pop();
+        assert_eq!(charges.add(charge.clone()), 3);
+        charges.charges.remove(0);
+        charges.assign_ids();
+        let ids: Vec<u32> = charges.charges.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [1, 3]);
+        assert_eq!(charges.add(charge), 4);
+    }
+}
@@ -0,0 +1,80 @@
+//! Fixed timestep bookkeeping
+
+use serde::{Deserialize, Serialize};
+
+/// Fixed step size and how many steps one frame may run
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Timestep {
+    /// Simulated seconds per step
+    pub dt: f32,
+    /// Steps one frame may run before the rest of the frame's time is dropped
+    pub max_substeps: usize,
+}
+
+/// Simulated time, advanced in fixed steps
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct SimulationClock {
+    /// Simulated seconds since the start
+    pub elapsed: f32,
+    /// Steps taken since the start
+    pub steps: usize,
+    accumulator: f32,
+}
+impl SimulationClock {
+    /// Returns how many fixed steps should be run to catch up with the real frame time
+    pub fn advance(&mut self, frame_dt: f32, time_scale: f32, timestep: Timestep) -> usize {
+        self.accumulator += frame_dt * time_scale;
+
+        let mut substeps = 0;
+        while self.accumulator >= timestep.dt && substeps < timestep.max_substeps {
+            self.accumulator -= timestep.dt;
+            substeps += 1;
+        }
+
+        // Drop the backlog instead of trying to catch up forever on slow frames
+        if substeps == timestep.max_substeps {
+            self.accumulator = 0.0;
+        }
+
+        return substeps;
+    }
+
+    /// Records one step of dt
+    pub fn tick(&mut self, dt: f32) {
+        self.elapsed += dt;
+        self.steps += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMESTEP: Timestep = Timestep {
+        dt: 0.01,
+        max_substeps: 100,
+    };
+
+    // Steps run over one second of frames at fps
+    fn steps_in_a_second(fps: usize, time_scale: f32) -> usize {
+        let mut clock = SimulationClock::default();
+        return (0..fps)
+            .map(|_| clock.advance(1.0 / fps as f32, time_scale, TIMESTEP))
+            .sum();
+    }
+
+    #[test]
+    fn steps_follow_time_not_frames() {
+        for fps in [24, 30, 60, 144, 240] {
+            let steps = steps_in_a_second(fps, 1.0);
+            assert!((99..=100).contains(&steps), "{} fps ran {} steps", fps, steps);
+        }
+        assert!((199..=200).contains(&steps_in_a_second(60, 2.0)));
+
+        // A long stall runs at most max_substeps and drops the rest
+        let mut clock = SimulationClock::default();
+        assert_eq!(clock.advance(5.0, 1.0, TIMESTEP), 100);
+        assert_eq!(clock.advance(0.0, 1.0, TIMESTEP), 0);
+    }
+}
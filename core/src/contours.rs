@@ -0,0 +1,248 @@
+//! Contour lines over scalar grids
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+// Potential spikes near charges so fixed spacing could otherwise ask for thousands of lines
+const MAX_LEVELS: usize = 64;
+
+/// Which values to draw contours at
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Levels {
+    /// Every multiple of the spacing within the data, at most 64 of them nearest the median
+    Spacing(f32),
+    /// Evenly spread between the 5th and 95th percentile of the potential
+    Auto(usize),
+}
+impl Levels {
+    /// Contour values for grid
+    pub fn values(&self, grid: &[Vec<f32>]) -> Vec<f32> {
+        let mut sorted: Vec<f32> = grid
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|v| v.is_finite())
+            .collect();
+        if sorted.is_empty() {
+            return vec![];
+        }
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        match *self {
+            Levels::Spacing(spacing) => {
+                if spacing <= 0.0 {
+                    return vec![];
+                }
+                let min = (sorted[0] / spacing).ceil() as i64;
+                let max = (sorted[sorted.len() - 1] / spacing).floor() as i64;
+                // Keep the levels nearest the median, most of the grid is there and not in the
+                // spikes at the charges
+                let median = (sorted[sorted.len() / 2] / spacing).round() as i64;
+                let cap = MAX_LEVELS as i64;
+                let high = (median + cap / 2).max(min + cap - 1).min(max);
+                let low = (high - cap + 1).max(min);
+                return (low..=high).map(|k| k as f32 * spacing).collect();
+            }
+            Levels::Auto(count) => {
+                let count = count.min(MAX_LEVELS);
+                let low = sorted[sorted.len() * 5 / 100];
+                let high = sorted[(sorted.len() * 95 / 100).min(sorted.len() - 1)];
+                return (1..=count)
+                    .map(|i| low + (high - low) * i as f32 / (count + 1) as f32)
+                    .collect();
+            }
+        }
+    }
+}
+
+/// Line segments where the grid crosses level, positioned using coords
+pub fn marching_squares(grid: &[Vec<f32>], coords: &[Vec<Vec2>], level: f32) -> Vec<[Vec2; 2]> {
+    let mut segments = vec![];
+    let height = coords.len();
+    if height < 2 {
+        return segments;
+    }
+    let width = coords[0].len();
+
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            // Corners anticlockwise from bottom left
+            let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+            let values = corners.map(|(cx, cy)| grid[cy][cx]);
+            if values.iter().any(|v| !v.is_finite()) {
+                continue;
+            }
+
+            let mut case = 0;
+            for (i, v) in values.iter().enumerate() {
+                if *v > level {
+                    case |= 1 << i;
+                }
+            }
+            if case == 0 || case == 15 {
+                continue;
+            }
+
+            // Crossing point on the edge from corner a to corner b
+            let edge = |a: usize, b: usize| -> Vec2 {
+                let pa = coords[corners[a].1][corners[a].0];
+                let pb = coords[corners[b].1][corners[b].0];
+                let t = (level - values[a]) / (values[b] - values[a]);
+                return pa + (pb - pa) * t;
+            };
+            let (bottom, right, top, left) = (edge(0, 1), edge(1, 2), edge(3, 2), edge(0, 3));
+
+            let centre_above = values.iter().sum::<f32>() / 4.0 > level;
+            match case {
+                1 | 14 => segments.push([left, bottom]),
+                2 | 13 => segments.push([bottom, right]),
+                3 | 12 => segments.push([left, right]),
+                4 | 11 => segments.push([right, top]),
+                6 | 9 => segments.push([bottom, top]),
+                7 | 8 => segments.push([left, top]),
+                // Saddles, resolved by the average of the cell
+                5 => {
+                    if centre_above {
+                        segments.push([left, top]);
+                        segments.push([bottom, right]);
+                    } else {
+                        segments.push([left, bottom]);
+                        segments.push([right, top]);
+                    }
+                }
+                10 => {
+                    if centre_above {
+                        segments.push([left, bottom]);
+                        segments.push([right, top]);
+                    } else {
+                        segments.push([left, top]);
+                        segments.push([bottom, right]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    return segments;
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    #[test]
+    fn spacing_caps_levels_around_the_data() {
+        let grid = vec![(0..=90).map(|i| 1.0 + i as f32 * 0.01).collect()];
+        let levels = Levels::Spacing(0.01).values(&grid);
+        assert_eq!(levels.len(), MAX_LEVELS);
+        assert!(levels.iter().all(|l| (0.999..=1.901).contains(l)));
+        assert!(levels.iter().any(|l| (l - 1.45).abs() < 1e-4));
+
+        // Under the cap every crossing is kept
+        let levels = Levels::Spacing(0.1).values(&grid);
+        assert_eq!(levels.len(), 10);
+    }
+
+    // One unit cell, values given anticlockwise from bottom left like the corners above
+    fn cell(values: [f32; 4], level: f32) -> Vec<[Vec2; 2]> {
+        let grid = vec![vec![values[0], values[1]], vec![values[3], values[2]]];
+        let coords = vec![
+            vec![vec2(0.0, 0.0), vec2(1.0, 0.0)],
+            vec![vec2(0.0, 1.0), vec2(1.0, 1.0)],
+        ];
+        return marching_squares(&grid, &coords, level);
+    }
+
+    fn joins(segments: &[[Vec2; 2]], a: Vec2, b: Vec2) -> bool {
+        let close = |p: Vec2, q: Vec2| p.distance(q) < 1e-6;
+        return segments
+            .iter()
+            .any(|[p, q]| (close(*p, a) && close(*q, b)) || (close(*p, b) && close(*q, a)));
+    }
+
+    const BOTTOM: Vec2 = Vec2::new(0.5, 0.0);
+    const RIGHT: Vec2 = Vec2::new(1.0, 0.5);
+    const TOP: Vec2 = Vec2::new(0.5, 1.0);
+    const LEFT: Vec2 = Vec2::new(0.0, 0.5);
+
+    #[test]
+    fn every_case_crosses_the_edges_that_change_sign() {
+        for case in 0..16 {
+            let values = [0, 1, 2, 3].map(|i| ((case >> i) & 1) as f32);
+            let segments = cell(values, 0.5);
+
+            let expected = match case {
+                0 | 15 => 0,
+                5 | 10 => 2,
+                _ => 1,
+            };
+            assert_eq!(segments.len(), expected, "case {}", case);
+
+            // With corners at 0 and 1 every crossing is the middle of an edge that changes sign
+            let edges = [(0, 1, BOTTOM), (1, 2, RIGHT), (3, 2, TOP), (0, 3, LEFT)];
+            for p in segments.iter().flatten() {
+                let edge = edges.iter().find(|(_, _, mid)| mid.distance(*p) < 1e-6);
+                let (a, b, _) = edge.unwrap_or_else(|| panic!("case {} at {}", case, p));
+                assert_ne!(values[*a], values[*b], "case {}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn crossings_are_interpolated() {
+        let segments = cell([0.0, 4.0, 4.0, 0.0], 1.0);
+        assert!(joins(&segments, vec2(0.25, 0.0), vec2(0.25, 1.0)));
+    }
+
+    // Edges of the unit cell a segment runs between, in a fixed order
+    fn sides(segments: &[[Vec2; 2]]) -> Vec<[&'static str; 2]> {
+        let side = |p: Vec2| {
+            if p.y == 0.0 {
+                return "bottom";
+            } else if p.x == 1.0 {
+                return "right";
+            } else if p.y == 1.0 {
+                return "top";
+            }
+            return "left";
+        };
+        let mut sides: Vec<[&str; 2]> = segments
+            .iter()
+            .map(|[a, b]| {
+                let mut pair = [side(*a), side(*b)];
+                pair.sort();
+                pair
+            })
+            .collect();
+        sides.sort();
+        return sides;
+    }
+
+    #[test]
+    fn saddles_follow_the_cell_average() {
+        // Bottom left and top right above the level, joined through the middle when it's above
+        let diagonal = [1.0, 0.0, 1.0, 0.0];
+        assert_eq!(
+            sides(&cell(diagonal, 0.4)),
+            [["bottom", "right"], ["left", "top"]]
+        );
+        assert_eq!(
+            sides(&cell(diagonal, 0.6)),
+            [["bottom", "left"], ["right", "top"]]
+        );
+
+        // The other diagonal
+        let anti_diagonal = [0.0, 1.0, 0.0, 1.0];
+        assert_eq!(
+            sides(&cell(anti_diagonal, 0.4)),
+            [["bottom", "left"], ["right", "top"]]
+        );
+        assert_eq!(
+            sides(&cell(anti_diagonal, 0.6)),
+            [["bottom", "right"], ["left", "top"]]
+        );
+    }
+}
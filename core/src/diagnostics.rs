@@ -0,0 +1,64 @@
+//! Energy and momentum measurements
+
+use glam::Vec2;
+
+use crate::{barnes_hut::Solver, charge::Charges};
+
+/// Conserved quantities at one moment
+///
+/// External fields are left out of the potential energy since they can vary in time, and with
+/// theta above 0 it carries the same approximation error as the forces
+#[derive(Clone, Copy, Default)]
+pub struct Snapshot {
+    /// Simulated time it was taken at
+    pub time: f32,
+    /// Sum of 1/2 m v^2
+    pub kinetic: f32,
+    /// Sum of the pairwise Coulomb potential energy
+    pub potential: f32,
+    /// Sum of m v
+    pub momentum: Vec2,
+    /// Sum of m (p x v) about the origin, out of the plane
+    pub angular_momentum: f32,
+}
+impl Snapshot {
+    /// Takes a snapshot of charges at time, with the potential found using solver
+    pub fn measure(charges: &Charges, bounds: [f32; 4], solver: Solver, time: f32) -> Self {
+        let mut kinetic = 0.0;
+        let mut momentum = Vec2::ZERO;
+        let mut angular_momentum = 0.0;
+        for c in charges.charges.iter() {
+            kinetic += 0.5 * c.m * c.v.length_squared();
+            momentum += c.m * c.v;
+            angular_momentum += c.m * c.p.perp_dot(c.v);
+        }
+
+        return Self {
+            time,
+            kinetic,
+            potential: charges.potential_energy(bounds, solver),
+            momentum,
+            angular_momentum,
+        };
+    }
+
+    /// Kinetic plus potential energy
+    pub fn total_energy(&self) -> f32 {
+        return self.kinetic + self.potential;
+    }
+
+    /// Relative change in energy (absolute if it started at zero) and change in momenta
+    pub fn drift(&self, initial: &Snapshot) -> (f32, Vec2, f32) {
+        let e0 = initial.total_energy();
+        let energy = if e0 == 0.0 {
+            self.total_energy()
+        } else {
+            (self.total_energy() - e0) / e0.abs()
+        };
+        return (
+            energy,
+            self.momentum - initial.momentum,
+            self.angular_momentum - initial.angular_momentum,
+        );
+    }
+}
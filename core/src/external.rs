@@ -0,0 +1,99 @@
+//! Applied electric fields
+
+use std::f32::consts::PI;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// How an applied field varies over space
+#[derive(Clone, Deserialize, Serialize)]
+pub enum Profile {
+    /// e everywhere
+    Uniform {
+        /// Field strength and direction
+        e: Vec2,
+    },
+    /// e at origin, changing by dx per unit x and dy per unit y
+    Gradient {
+        /// Field at origin
+        e: Vec2,
+        /// Where the field is e
+        origin: Vec2,
+        /// Change in the field per unit x
+        dx: Vec2,
+        /// Change in the field per unit y
+        dy: Vec2,
+    },
+}
+
+/// How an applied field varies over time, as a factor on its profile
+#[derive(Clone, Deserialize, Serialize)]
+pub enum Modulation {
+    /// Always full strength
+    Constant,
+    /// Swings between full strength and reversed as sin(2 pi frequency t + phase)
+    Sine {
+        /// Cycles per simulated second
+        frequency: f32,
+        /// Offset in radians
+        phase: f32,
+    },
+    /// Flips between full strength and reversed with the sign of the sine above
+    Square {
+        /// Cycles per simulated second
+        frequency: f32,
+        /// Offset in radians
+        phase: f32,
+    },
+    /// Rises linearly from 0 to full strength over duration then holds
+    Ramp {
+        /// Simulated seconds to reach full strength, 0 or less starts at full strength
+        duration: f32,
+    },
+}
+impl Modulation {
+    /// Factor on the profile at time t, between -1 and 1
+    pub fn factor(&self, t: f32) -> f32 {
+        match *self {
+            Modulation::Constant => 1.0,
+            Modulation::Sine { frequency, phase } => f32::sin(2.0 * PI * frequency * t + phase),
+            Modulation::Square { frequency, phase } => {
+                let s = f32::sin(2.0 * PI * frequency * t + phase);
+                if s >= 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Modulation::Ramp { duration } => {
+                if duration <= 0.0 {
+                    1.0
+                } else {
+                    (t / duration).clamp(0.0, 1.0)
+                }
+            }
+        }
+    }
+}
+
+/// An applied electric field, a spatial profile scaled over time by a modulation
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ExternalField {
+    /// Field at full strength
+    pub profile: Profile,
+    /// Strength over time
+    pub modulation: Modulation,
+}
+impl ExternalField {
+    /// E at a point and time
+    pub fn calc_e(&self, at: Vec2, t: f32) -> Vec2 {
+        let e = match self.profile {
+            Profile::Uniform { e } => e,
+            Profile::Gradient { e, origin, dx, dy } => {
+                let offset = at - origin;
+                e + dx * offset.x + dy * offset.y
+            }
+        };
+        return e * self.modulation.factor(t);
+    }
+}
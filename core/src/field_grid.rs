@@ -0,0 +1,226 @@
+//! Field values on a regular grid
+
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::sampling::Sampling;
+
+/// Finite difference scheme used for divergence and curl
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Stencil {
+    /// First order, from each cell to the next
+    Forward,
+    /// First order, from the previous cell to each one
+    Backward,
+    /// Second order, across the cells either side
+    Central,
+    /// Fourth order, drops to central differences next to the edges
+    FivePoint,
+}
+
+/// Fields sampled at the centres of a regular grid of cells centred on the origin
+///
+/// Every grid is indexed `[y][x]` with rows running from the bottom up, and `coords[y][x]`
+/// is the centre of that cell in simulation units
+pub struct FieldGrid {
+    size: [usize; 2],
+    resolution: usize,
+    /// Electric field
+    pub field: Vec<Vec<Vec2>>,
+    /// Magnetic field, out of the plane
+    pub magnetic: Vec<Vec<f32>>,
+    /// Electric potential
+    pub potential: Vec<Vec<f32>>,
+    /// Divergence of the electric field, filled by [`FieldGrid::compute_derivatives`]
+    pub divergence: Vec<Vec<f32>>,
+    /// Curl of the electric field, filled by [`FieldGrid::compute_derivatives`]
+    pub curl: Vec<Vec<f32>>,
+    /// Centre of each cell
+    pub coords: Vec<Vec<Vec2>>,
+}
+impl FieldGrid {
+    /// A grid covering `size` simulation units with `resolution` cells per unit
+    pub fn new(size: [usize; 2], resolution: usize) -> Self {
+        let [width, height] = [size[0] * resolution, size[1] * resolution];
+        let zeros = vec![vec![0.0; width]; height];
+
+        let coords = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| {
+                        vec2(
+                            (x as f32 + 0.5) - (width as f32 / 2.0),
+                            (y as f32 + 0.5) - (height as f32 / 2.0),
+                        ) / resolution as f32
+                    })
+                    .collect()
+            })
+            .collect();
+
+        return Self {
+            size,
+            resolution,
+            field: vec![vec![vec2(0.0, 1.0); width]; height],
+            magnetic: zeros.clone(),
+            potential: zeros.clone(),
+            divergence: zeros.clone(),
+            curl: zeros,
+            coords,
+        };
+    }
+
+    /// Simulation units covered as `[width, height]`
+    pub fn get_size(&self) -> [usize; 2] {
+        return self.size;
+    }
+
+    /// Number of cells as `[width, height]`
+    pub fn get_shape(&self) -> [usize; 2] {
+        return [
+            self.size[0] * self.resolution,
+            self.size[1] * self.resolution,
+        ];
+    }
+
+    /// Extent of the cell centres as `[min_x, max_x, min_y, max_y]`
+    pub fn get_bounds(&self) -> [f32; 4] {
+        let [width, height] = self.get_shape();
+        let bl = self.coords[0][0];
+        let tr = self.coords[height - 1][width - 1];
+        return [bl.x, tr.x, bl.y, tr.y];
+    }
+
+    /// Cells per simulation unit
+    pub fn get_resolution(&self) -> usize {
+        return self.resolution;
+    }
+
+    /// Fills `divergence` and `curl` (its z component) from `field`
+    pub fn compute_derivatives(&mut self, stencil: Stencil) {
+        let [width, height] = self.get_shape();
+        let h = 1.0 / self.resolution as f32;
+
+        for y in 0..height {
+            for x in 0..width {
+                let d_dx = derivative(|i| self.field[y][i], x, width, h, stencil);
+                let d_dy = derivative(|i| self.field[i][x], y, height, h, stencil);
+                self.divergence[y][x] = d_dx.x + d_dy.y;
+                self.curl[y][x] = d_dx.y - d_dy.x;
+            }
+        }
+    }
+
+    // Position in grid cells, so coords[y][x] maps to (x, y)
+    fn to_grid(&self, pos: Vec2) -> Vec2 {
+        let [width, height] = self.get_shape();
+        let half = vec2(width as f32, height as f32) / 2.0;
+        return pos * self.resolution as f32 + half - Vec2::splat(0.5);
+    }
+
+    /// E at a point in simulation units
+    pub fn sample(&self, pos: Vec2, sampling: Sampling) -> Option<Vec2> {
+        return sampling.sample(&self.field, self.to_grid(pos));
+    }
+
+    /// B at a point in simulation units
+    pub fn sample_magnetic(&self, pos: Vec2, sampling: Sampling) -> Option<f32> {
+        return sampling.sample(&self.magnetic, self.to_grid(pos));
+    }
+
+    /// Potential at a point in simulation units
+    pub fn sample_potential(&self, pos: Vec2, sampling: Sampling) -> Option<f32> {
+        return sampling.sample(&self.potential, self.to_grid(pos));
+    }
+}
+
+// Derivative at i of the n values returned by get, spaced h apart
+fn derivative<F>(get: F, i: usize, n: usize, h: f32, stencil: Stencil) -> Vec2
+where
+    F: Fn(usize) -> Vec2,
+{
+    if n < 2 {
+        return Vec2::ZERO;
+    }
+
+    let stencil = match stencil {
+        Stencil::FivePoint if i < 2 || i + 2 >= n => Stencil::Central,
+        _ => stencil,
+    };
+    let stencil = match stencil {
+        Stencil::Central | Stencil::Backward if i == 0 => Stencil::Forward,
+        Stencil::Central | Stencil::Forward if i == n - 1 => Stencil::Backward,
+        _ => stencil,
+    };
+
+    match stencil {
+        Stencil::Forward => (get(i + 1) - get(i)) / h,
+        Stencil::Backward => (get(i) - get(i - 1)) / h,
+        Stencil::Central => (get(i + 1) - get(i - 1)) / (2.0 * h),
+        Stencil::FivePoint => {
+            (get(i - 2) - get(i + 2) + 8.0 * (get(i + 1) - get(i - 1))) / (12.0 * h)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn five_point_is_exact_on_a_cubic() {
+        // f(x) = x^3 sampled every 0.5, so f'(2) = 12 and central differences add h^2 f'''/6
+        let h = 0.5;
+        let get = |i: usize| vec2((i as f32 * h).powi(3), 0.0);
+
+        let central = derivative(get, 4, 10, h, Stencil::Central);
+        assert!((central.x - (12.0 + h * h)).abs() < 1e-4);
+        let five_point = derivative(get, 4, 10, h, Stencil::FivePoint);
+        assert!((five_point.x - 12.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn five_point_falls_back_next_to_the_edges() {
+        let h = 0.5;
+        let get = |i: usize| vec2((i as f32 * h).powi(3), 0.0);
+        // Central differences in turn go one sided at the very ends
+        for i in [0, 1, 8, 9] {
+            let central = derivative(get, i, 10, h, Stencil::Central);
+            assert_eq!(derivative(get, i, 10, h, Stencil::FivePoint), central);
+        }
+    }
+
+    #[test]
+    fn rotation_has_constant_curl_and_no_divergence() {
+        let mut grid = FieldGrid::new([4, 3], 2);
+        grid.field = grid
+            .coords
+            .iter()
+            .map(|row| row.iter().map(|c| c.perp()).collect())
+            .collect();
+
+        for stencil in [
+            Stencil::Forward,
+            Stencil::Backward,
+            Stencil::Central,
+            Stencil::FivePoint,
+        ] {
+            grid.compute_derivatives(stencil);
+            assert!(grid.divergence.iter().flatten().all(|d| d.abs() < 1e-4));
+            assert!(grid.curl.iter().flatten().all(|c| (c - 2.0).abs() < 1e-4));
+        }
+    }
+
+    #[test]
+    fn cell_centres_are_symmetric_about_the_origin() {
+        let grid = FieldGrid::new([4, 3], 2);
+        let [min_x, max_x, min_y, max_y] = grid.get_bounds();
+        assert_eq!((min_x, max_x, min_y, max_y), (-1.75, 1.75, -1.25, 1.25));
+
+        let [width, height] = grid.get_shape();
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(grid.to_grid(grid.coords[y][x]), vec2(x as f32, y as f32));
+            }
+        }
+    }
+}
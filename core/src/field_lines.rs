@@ -0,0 +1,113 @@
+//! Electric field line tracing
+
+use std::f32::consts::PI;
+
+use glam::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::charge::Charges;
+
+const MAX_STEPS: usize = 10_000;
+
+/// How field lines are seeded and traced
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct FieldLines {
+    /// Whether lines are drawn at all
+    pub enabled: bool,
+    /// Lines per unit of charge, every seeding charge gets at least one
+    pub lines_per_charge: f32,
+    /// Longest a line may get, in simulation units
+    pub max_length: f32,
+    /// Longest step along a line, also where lines start and stop around charges
+    pub max_step: f32,
+    /// Shortest step, taken even if it misses tolerance
+    pub min_step: f32,
+    /// Largest position error allowed per step
+    pub tolerance: f32,
+}
+
+/// Lines start on positive charges, N per unit of charge, and follow E until they reach a
+/// negative charge or leave the bounds. With no positive charges they run back from the negatives.
+pub fn trace<F>(
+    charges: &Charges,
+    e_field: F,
+    bounds: [f32; 4],
+    lines: FieldLines,
+) -> Vec<Vec<Vec2>>
+where
+    F: Fn(Vec2) -> Vec2,
+{
+    let has_positive = charges.charges.iter().any(|c| c.q > 0.0);
+    let sign = if has_positive { 1.0 } else { -1.0 };
+    let direction = |at: Vec2| e_field(at).normalize_or_zero() * sign;
+    let is_sink = |at: Vec2| {
+        charges
+            .charges
+            .iter()
+            .any(|c| c.q * sign < 0.0 && c.p.distance(at) <= c.radius.max(lines.max_step))
+    };
+
+    let mut polylines = vec![];
+    for c in charges.charges.iter() {
+        if c.q * sign <= 0.0 {
+            continue;
+        }
+
+        let num_lines = ((c.q.abs() * lines.lines_per_charge).round() as usize).max(1);
+        let seed_radius = c.radius.max(lines.max_step);
+        for i in 0..num_lines {
+            let angle = 2.0 * PI * i as f32 / num_lines as f32;
+            let start = c.p + vec2(angle.cos(), angle.sin()) * seed_radius;
+            polylines.push(trace_line(start, direction, is_sink, bounds, lines));
+        }
+    }
+
+    return polylines;
+}
+
+// Adaptive Heun-Euler integration along a unit direction field
+fn trace_line<D, S>(
+    start: Vec2,
+    direction: D,
+    is_sink: S,
+    bounds: [f32; 4],
+    lines: FieldLines,
+) -> Vec<Vec2>
+where
+    D: Fn(Vec2) -> Vec2,
+    S: Fn(Vec2) -> bool,
+{
+    let [min_x, max_x, min_y, max_y] = bounds;
+    let mut points = vec![start];
+    let mut p = start;
+    let mut h = lines.max_step;
+    let mut length = 0.0;
+
+    for _ in 0..MAX_STEPS {
+        let k1 = direction(p);
+        if k1 == Vec2::ZERO {
+            break;
+        }
+        let k2 = direction(p + k1 * h);
+        let err = ((k2 - k1) * (h / 2.0)).length();
+
+        if err > lines.tolerance && h > lines.min_step {
+            h = (h / 2.0).max(lines.min_step);
+            continue;
+        }
+
+        p += (k1 + k2) * (h / 2.0);
+        length += h;
+        points.push(p);
+
+        let outside = p.x < min_x || p.x > max_x || p.y < min_y || p.y > max_y;
+        if outside || !p.is_finite() || length >= lines.max_length || is_sink(p) {
+            break;
+        }
+        if err < lines.tolerance / 4.0 {
+            h = (h * 2.0).min(lines.max_step);
+        }
+    }
+
+    return points;
+}
@@ -0,0 +1,185 @@
+//! Writing a [`FieldGrid`] out for other tools
+//!
+//! Every format carries the same quantities, see [`quantities`], with rows running from the
+//! bottom of the grid up and x varying fastest.
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::field_grid::FieldGrid;
+
+// .npy headers are padded so the data starts on this boundary
+const NPY_ALIGNMENT: usize = 64;
+
+/// Describes a grid for formats that can't hold it themselves, like .npy
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Metadata {
+    /// Simulation units covered, as `[width, height]`
+    pub size: [usize; 2],
+    /// Cells per simulation unit
+    pub resolution: usize,
+    /// Array shape as `[rows, columns]`, the order NumPy uses
+    pub shape: [usize; 2],
+    /// Extent of the cell centres as `[min_x, max_x, min_y, max_y]`
+    pub bounds: [f32; 4],
+    /// Names of the exported quantities, in the order they were written
+    pub quantities: Vec<String>,
+}
+impl Metadata {
+    /// Metadata for grid, with no quantities listed yet
+    pub fn new(grid: &FieldGrid) -> Self {
+        let [width, height] = grid.get_shape();
+        return Self {
+            size: grid.get_size(),
+            resolution: grid.get_resolution(),
+            shape: [height, width],
+            bounds: grid.get_bounds(),
+            quantities: quantities(grid)
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        };
+    }
+}
+
+/// Named scalar grids: cell coordinates, E components, |E| and the potential
+pub fn quantities(grid: &FieldGrid) -> Vec<(&'static str, Vec<Vec<f32>>)> {
+    return vec![
+        ("x", map(&grid.coords, |c| c.x)),
+        ("y", map(&grid.coords, |c| c.y)),
+        ("ex", map(&grid.field, |e| e.x)),
+        ("ey", map(&grid.field, |e| e.y)),
+        ("magnitude", map(&grid.field, |e| e.length())),
+        ("potential", grid.potential.clone()),
+    ];
+}
+
+fn map<T, F: Fn(&T) -> f32>(grid: &[Vec<T>], f: F) -> Vec<Vec<f32>> {
+    return grid
+        .iter()
+        .map(|row| row.iter().map(&f).collect())
+        .collect();
+}
+
+/// One line per cell with its grid index, size and resolution go in leading `#` comments
+pub fn write_csv<W: Write>(grid: &FieldGrid, out: &mut W) -> io::Result<()> {
+    let metadata = Metadata::new(grid);
+    let quantities = quantities(grid);
+    writeln!(out, "# size {} {}", metadata.size[0], metadata.size[1])?;
+    writeln!(out, "# resolution {}", metadata.resolution)?;
+
+    let names: Vec<&str> = quantities.iter().map(|(name, _)| *name).collect();
+    writeln!(out, "i,j,{}", names.join(","))?;
+    let [height, width] = metadata.shape;
+    for j in 0..height {
+        for i in 0..width {
+            write!(out, "{},{}", i, j)?;
+            for (_, values) in quantities.iter() {
+                write!(out, ",{}", values[j][i])?;
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// A single NumPy v1.0 array of little endian f32 with shape `[rows, columns]`
+pub fn write_npy<W: Write>(values: &[Vec<f32>], out: &mut W) -> io::Result<()> {
+    let (height, width) = (values.len(), values.first().map_or(0, |row| row.len()));
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        height, width
+    );
+    // 10 bytes of magic, version and length come before the header, which ends in a newline
+    let unpadded = 10 + header.len() + 1;
+    let padding = (NPY_ALIGNMENT - unpadded % NPY_ALIGNMENT) % NPY_ALIGNMENT;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for row in values {
+        for value in row {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Legacy ASCII VTK structured grid, size and resolution are stored as field data
+pub fn write_vtk<W: Write>(grid: &FieldGrid, out: &mut W) -> io::Result<()> {
+    let metadata = Metadata::new(grid);
+    let [height, width] = metadata.shape;
+    let points = width * height;
+
+    writeln!(out, "# vtk DataFile Version 3.0")?;
+    writeln!(
+        out,
+        "Electric field, size {} {}, resolution {}",
+        metadata.size[0], metadata.size[1], metadata.resolution
+    )?;
+    writeln!(out, "ASCII")?;
+    writeln!(out, "DATASET STRUCTURED_GRID")?;
+    writeln!(out, "FIELD FieldData 2")?;
+    writeln!(out, "size 2 1 int")?;
+    writeln!(out, "{} {}", metadata.size[0], metadata.size[1])?;
+    writeln!(out, "resolution 1 1 int")?;
+    writeln!(out, "{}", metadata.resolution)?;
+    writeln!(out, "DIMENSIONS {} {} 1", width, height)?;
+
+    writeln!(out, "POINTS {} float", points)?;
+    for c in grid.coords.iter().flatten() {
+        writeln!(out, "{} {} 0", c.x, c.y)?;
+    }
+
+    writeln!(out, "POINT_DATA {}", points)?;
+    writeln!(out, "VECTORS E float")?;
+    for e in grid.field.iter().flatten() {
+        writeln!(out, "{} {} 0", e.x, e.y)?;
+    }
+    let scalars = [
+        ("magnitude", map(&grid.field, |e| e.length())),
+        ("potential", grid.potential.clone()),
+    ];
+    for (name, values) in scalars {
+        writeln!(out, "SCALARS {} float 1", name)?;
+        writeln!(out, "LOOKUP_TABLE default")?;
+        for value in values.iter().flatten() {
+            writeln!(out, "{}", value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_header_is_aligned_and_parses() {
+        let values = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.5]];
+        let mut out = vec![];
+        write_npy(&values, &mut out).unwrap();
+
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([out[8], out[9]]) as usize;
+        let data_start = 10 + header_len;
+        assert_eq!(data_start % NPY_ALIGNMENT, 0);
+
+        let header = std::str::from_utf8(&out[10..data_start]).unwrap();
+        assert!(header.ends_with('\n'));
+        let header = header.trim_end();
+        assert!(header.starts_with('{') && header.ends_with('}'));
+        assert!(header.contains("'descr': '<f4'"));
+        assert!(header.contains("'fortran_order': False"));
+        assert!(header.contains("'shape': (2, 3)"));
+
+        let data: Vec<f32> = out[data_start..]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(data, [1.0, 2.0, 3.0, 4.0, 5.0, 6.5]);
+    }
+}
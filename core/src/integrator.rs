@@ -0,0 +1,324 @@
+//! Time integrators
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+// min_dt is floored at this fraction of dt so the step size can never reach zero
+const MIN_DT_FRACTION: f32 = 1e-5;
+// Consecutive rejected tries before the rest of the step is given up on
+const MAX_REJECTIONS: usize = 64;
+
+// Dormand-Prince 5(4) tableau
+const DP_C: [f32; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+const DP_A: [[f32; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+        0.0,
+        0.0,
+    ],
+    [
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+        0.0,
+    ],
+    [
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+// Difference between the 5th and 4th order weights
+const DP_E: [f32; 7] = [
+    71.0 / 57600.0,
+    0.0,
+    -71.0 / 16695.0,
+    71.0 / 1920.0,
+    -17253.0 / 339200.0,
+    22.0 / 525.0,
+    -1.0 / 40.0,
+];
+
+/// Scheme used to advance positions and velocities
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Integrator {
+    /// First order, updates v then moves with the new v, cheap and keeps orbits bounded
+    SemiImplicitEuler,
+    /// Second order and symplectic, the usual choice for long runs
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, accurate per step but energy slowly drifts
+    RK4,
+    /// Adaptive Dormand-Prince, splits each step into as many sub steps as needed
+    RK45 {
+        /// Error allowed per sub step, relative to 1 + the size of each position and velocity
+        tolerance: f32,
+        /// Shortest sub step in simulated seconds, taken even if it misses tolerance. It's
+        /// never below a fixed fraction of the step so a step can't stall
+        min_dt: f32,
+    },
+}
+impl Integrator {
+    /// acc is called with the positions, velocities and the time elapsed since the start of the step
+    ///
+    /// Returns the accelerations at the start of the step, which every scheme evaluates first
+    pub fn step<F>(&self, p: &mut [Vec2], v: &mut [Vec2], dt: f32, acc: F) -> Vec<Vec2>
+    where
+        F: Fn(&[Vec2], &[Vec2], f32) -> Vec<Vec2>,
+    {
+        match *self {
+            Integrator::SemiImplicitEuler => semi_implicit_euler(p, v, dt, &acc),
+            Integrator::VelocityVerlet => velocity_verlet(p, v, dt, &acc),
+            Integrator::RK4 => rk4(p, v, dt, &acc),
+            Integrator::RK45 { tolerance, min_dt } => rk45(p, v, dt, tolerance, min_dt, &acc),
+        }
+    }
+}
+
+fn semi_implicit_euler<F>(p: &mut [Vec2], v: &mut [Vec2], dt: f32, acc: &F) -> Vec<Vec2>
+where
+    F: Fn(&[Vec2], &[Vec2], f32) -> Vec<Vec2>,
+{
+    let a = acc(p, v, 0.0);
+    for i in 0..p.len() {
+        v[i] += a[i] * dt;
+        p[i] += v[i] * dt;
+    }
+    return a;
+}
+
+// Kick-drift-kick leapfrog
+fn velocity_verlet<F>(p: &mut [Vec2], v: &mut [Vec2], dt: f32, acc: &F) -> Vec<Vec2>
+where
+    F: Fn(&[Vec2], &[Vec2], f32) -> Vec<Vec2>,
+{
+    let half_dt = dt * 0.5;
+
+    let start = acc(p, v, 0.0);
+    for i in 0..p.len() {
+        v[i] += start[i] * half_dt;
+        p[i] += v[i] * dt;
+    }
+
+    let a = acc(p, v, dt);
+    for i in 0..p.len() {
+        v[i] += a[i] * half_dt;
+    }
+    return start;
+}
+
+fn rk4<F>(p: &mut [Vec2], v: &mut [Vec2], dt: f32, acc: &F) -> Vec<Vec2>
+where
+    F: Fn(&[Vec2], &[Vec2], f32) -> Vec<Vec2>,
+{
+    let half_dt = dt * 0.5;
+
+    let k1_p = v.to_vec();
+    let k1_v = acc(p, v, 0.0);
+
+    let (p2, v2) = (offset(p, &k1_p, half_dt), offset(v, &k1_v, half_dt));
+    let k2_v = acc(&p2, &v2, half_dt);
+    let k2_p = v2;
+
+    let (p3, v3) = (offset(p, &k2_p, half_dt), offset(v, &k2_v, half_dt));
+    let k3_v = acc(&p3, &v3, half_dt);
+    let k3_p = v3;
+
+    let (p4, v4) = (offset(p, &k3_p, dt), offset(v, &k3_v, dt));
+    let k4_v = acc(&p4, &v4, dt);
+    let k4_p = v4;
+
+    for i in 0..p.len() {
+        p[i] += (k1_p[i] + 2.0 * k2_p[i] + 2.0 * k3_p[i] + k4_p[i]) * (dt / 6.0);
+        v[i] += (k1_v[i] + 2.0 * k2_v[i] + 2.0 * k3_v[i] + k4_v[i]) * (dt / 6.0);
+    }
+    return k1_v;
+}
+
+// Adaptive Dormand-Prince, takes as many sub steps as needed to cover dt
+//
+// Steps are accepted once h is down to min_dt even if the error is still too big, unless a
+// stage came out NaN or infinite. If that keeps happening the charges are left at the last
+// good sub step rather than spinning forever or stepping into NaNs
+fn rk45<F>(
+    p: &mut [Vec2],
+    v: &mut [Vec2],
+    dt: f32,
+    tolerance: f32,
+    min_dt: f32,
+    acc: &F,
+) -> Vec<Vec2>
+where
+    F: Fn(&[Vec2], &[Vec2], f32) -> Vec<Vec2>,
+{
+    let n = p.len();
+    // First stage of the first try, rejected tries all start from the same state
+    let mut start = None;
+    let min_dt = min_dt.max(dt * MIN_DT_FRACTION);
+    let mut t = 0.0;
+    let mut h = dt;
+    let mut rejections = 0;
+
+    while t < dt {
+        h = h.min(dt - t);
+
+        let mut k_p: Vec<Vec<Vec2>> = Vec::with_capacity(DP_C.len());
+        let mut k_v: Vec<Vec<Vec2>> = Vec::with_capacity(DP_C.len());
+        for s in 0..DP_C.len() {
+            let mut stage_p = p.to_vec();
+            let mut stage_v = v.to_vec();
+            for j in 0..s {
+                let a = DP_A[s][j];
+                if a == 0.0 {
+                    continue;
+                }
+                for i in 0..n {
+                    stage_p[i] += k_p[j][i] * (a * h);
+                    stage_v[i] += k_v[j][i] * (a * h);
+                }
+            }
+            let k = acc(&stage_p, &stage_v, t + DP_C[s] * h);
+            if start.is_none() {
+                start = Some(k.clone());
+            }
+            k_v.push(k);
+            k_p.push(stage_v);
+        }
+
+        let mut err: f32 = 0.0;
+        let mut blew_up = false;
+        for i in 0..n {
+            let mut err_p = Vec2::ZERO;
+            let mut err_v = Vec2::ZERO;
+            for s in 0..DP_C.len() {
+                err_p += k_p[s][i] * DP_E[s];
+                err_v += k_v[s][i] * DP_E[s];
+            }
+            // A stage that blew up leaves NaN or inf here, which f32::max would quietly drop
+            if !err_p.is_finite() || !err_v.is_finite() {
+                blew_up = true;
+                break;
+            }
+            let scale_p = tolerance * (1.0 + p[i].abs().max_element());
+            let scale_v = tolerance * (1.0 + v[i].abs().max_element());
+            err = err
+                .max((err_p * h).abs().max_element() / scale_p)
+                .max((err_v * h).abs().max_element() / scale_v);
+        }
+
+        if !blew_up && (err <= 1.0 || h <= min_dt) {
+            for i in 0..n {
+                let mut d_p = Vec2::ZERO;
+                let mut d_v = Vec2::ZERO;
+                for s in 0..DP_C.len() - 1 {
+                    d_p += k_p[s][i] * DP_A[6][s];
+                    d_v += k_v[s][i] * DP_A[6][s];
+                }
+                p[i] += d_p * h;
+                v[i] += d_v * h;
+            }
+            t += h;
+            rejections = 0;
+        } else {
+            rejections += 1;
+            if rejections > MAX_REJECTIONS {
+                break;
+            }
+        }
+
+        let factor = if blew_up {
+            0.2
+        } else if err == 0.0 {
+            5.0
+        } else {
+            (0.9 * err.powf(-0.2)).clamp(0.2, 5.0)
+        };
+        h = (h * factor).max(min_dt);
+    }
+    return start.unwrap_or_else(|| acc(p, v, 0.0));
+}
+
+fn offset(y: &[Vec2], k: &[Vec2], h: f32) -> Vec<Vec2> {
+    return y.iter().zip(k).map(|(y, k)| *y + *k * h).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    // A unit mass circling a fixed unit attractor, a = -p / |p|^3
+    fn kepler(p: &[Vec2], _: &[Vec2], _: f32) -> Vec<Vec2> {
+        return p.iter().map(|p| -*p / p.length().powi(3)).collect();
+    }
+
+    fn energy(p: Vec2, v: Vec2) -> f32 {
+        return 0.5 * v.length_squared() - 1.0 / p.length();
+    }
+
+    fn orbit_drift(integrator: Integrator) -> f32 {
+        let (mut p, mut v) = ([vec2(1.0, 0.0)], [vec2(0.0, 1.0)]);
+        let e0 = energy(p[0], v[0]);
+        // About three orbits
+        for _ in 0..2000 {
+            integrator.step(&mut p, &mut v, 0.01, kepler);
+        }
+        return ((energy(p[0], v[0]) - e0) / e0).abs();
+    }
+
+    #[test]
+    fn verlet_conserves_orbit_energy() {
+        assert!(orbit_drift(Integrator::VelocityVerlet) < 1e-4);
+    }
+
+    #[test]
+    fn rk4_conserves_orbit_energy() {
+        assert!(orbit_drift(Integrator::RK4) < 1e-5);
+    }
+
+    #[test]
+    fn rk45_conserves_orbit_energy() {
+        let integrator = Integrator::RK45 {
+            tolerance: 1e-6,
+            min_dt: 1e-5,
+        };
+        assert!(orbit_drift(integrator) < 1e-4);
+    }
+
+    #[test]
+    fn rk45_finishes_with_zero_min_dt() {
+        // No tolerance can be met, so every sub step ends up at the floored min_dt
+        let integrator = Integrator::RK45 {
+            tolerance: 0.0,
+            min_dt: 0.0,
+        };
+        let (mut p, mut v) = ([vec2(1.0, 0.0)], [vec2(0.0, 1.0)]);
+        integrator.step(&mut p, &mut v, 0.01, kepler);
+        assert!(p[0].is_finite() && v[0].is_finite());
+        assert!((energy(p[0], v[0]) + 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn rk45_rejects_nan_stages() {
+        let integrator = Integrator::RK45 {
+            tolerance: 1e-6,
+            min_dt: 0.0,
+        };
+        let (mut p, mut v) = ([vec2(1.0, 0.0)], [vec2(0.0, 1.0)]);
+        integrator.step(&mut p, &mut v, 0.01, |p, _, _| vec![Vec2::NAN; p.len()]);
+        assert_eq!(p[0], vec2(1.0, 0.0));
+        assert_eq!(v[0], vec2(0.0, 1.0));
+    }
+}
@@ -0,0 +1,260 @@
+//! Coulomb interaction between charges
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::charge::Charge;
+
+/// What happens when two charges touch
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Encounter {
+    /// Nothing happens, charges pass through each other with the force capped by min_distance
+    Clamp,
+    /// The charges become one, conserving charge, mass and momentum, unless either is pinned in
+    /// which case the result stays pinned where it was
+    Merge,
+    /// The charges bounce off each other, pinned charges acting as walls
+    Elastic,
+    /// Opposite charges remove each other, like charges pass through as with Clamp
+    Annihilate,
+}
+
+/// Charge to charge forces and close encounters
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Interaction {
+    /// Plummer softening length in simulation units, forces go as r / (r^2 + softening^2)^(3/2)
+    /// so they fall to zero at close range instead of diverging. 0 gives plain Coulomb
+    pub softening: f32,
+    /// Distances below this many simulation units are treated as this far when finding forces,
+    /// capping them. 0 leaves them uncapped
+    pub min_distance: f32,
+    /// What happens when two charges come within the sum of their radii
+    pub encounter: Encounter,
+}
+impl Default for Interaction {
+    fn default() -> Self {
+        Self {
+            softening: 0.0,
+            min_distance: 0.0,
+            encounter: Encounter::Clamp,
+        }
+    }
+}
+impl Interaction {
+    /// Plummer softened Coulomb force on b from a, with the distance floored at min_distance
+    pub fn calc_e_force(&self, a_q: f32, a_p: Vec2, b_q: f32, b_p: Vec2) -> Vec2 {
+        let dir = b_p - a_p;
+        let dist_squared = dir.length_squared();
+        if dist_squared == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let dist = f32::sqrt(dist_squared);
+        let norm_dir = dir / dist;
+        let dist = dist.max(self.min_distance);
+        let soft_squared = dist * dist + self.softening * self.softening;
+        let f = (a_q * b_q) * dist / (soft_squared * f32::sqrt(soft_squared));
+        return norm_dir * f;
+    }
+
+    /// Potential energy of the pair, matching the softening and floor used for the force
+    pub fn calc_potential(&self, a_q: f32, a_p: Vec2, b_q: f32, b_p: Vec2) -> f32 {
+        let dist = a_p.distance(b_p).max(self.min_distance);
+        let soft_squared = dist * dist + self.softening * self.softening;
+        if soft_squared == 0.0 {
+            return 0.0;
+        }
+        return (a_q * b_q) / f32::sqrt(soft_squared);
+    }
+
+    /// Applies the encounter rule to every touching pair
+    pub fn resolve(&self, charges: &mut Vec<Charge>) {
+        if matches!(self.encounter, Encounter::Clamp) {
+            return;
+        }
+
+        let mut i = 0;
+        while i < charges.len() {
+            let mut j = i + 1;
+            while j < charges.len() {
+                let (a, b) = (&charges[i], &charges[j]);
+                let contact = self.min_distance.max(a.radius + b.radius);
+                if a.p.distance(b.p) >= contact {
+                    j += 1;
+                    continue;
+                }
+
+                match self.encounter {
+                    Encounter::Merge => {
+                        let b = charges.remove(j);
+                        charges[i] = merge(&charges[i], &b);
+                        continue;
+                    }
+                    Encounter::Elastic => {
+                        let (left, right) = charges.split_at_mut(j);
+                        bounce(&mut left[i], &mut right[0], contact);
+                    }
+                    Encounter::Annihilate => {
+                        if charges[i].q * charges[j].q < 0.0 {
+                            charges.remove(j);
+                            charges.remove(i);
+                            j = i + 1;
+                            continue;
+                        }
+                    }
+                    Encounter::Clamp => {}
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+    }
+}
+
+// The merged charge carries on as a, keeping its id
+fn merge(a: &Charge, b: &Charge) -> Charge {
+    let m = a.m + b.m;
+    let radius = f32::sqrt(a.radius * a.radius + b.radius * b.radius);
+
+    // A pinned charge swallows whatever hits it
+    if a.fixed || b.fixed {
+        let p = if a.fixed { a.p } else { b.p };
+        let mut merged = Charge::new(a.q + b.q, m, p, Vec2::ZERO, radius);
+        merged.id = a.id;
+        merged.set_fixed(true);
+        return merged;
+    }
+
+    let p = (a.p * a.m + b.p * b.m) / m;
+    let v = (a.v * a.m + b.v * b.m) / m;
+    let mut merged = Charge::new(a.q + b.q, m, p, v, radius);
+    merged.id = a.id;
+    return merged;
+}
+
+fn bounce(a: &mut Charge, b: &mut Charge, contact: f32) {
+    if a.fixed && b.fixed {
+        return;
+    }
+
+    let dir = b.p - a.p;
+    let dist = dir.length();
+    let normal = if dist == 0.0 { Vec2::X } else { dir / dist };
+    // Pinned charges behave as if they had infinite mass
+    let inv_a = if a.fixed { 0.0 } else { 1.0 / a.m };
+    let inv_b = if b.fixed { 0.0 } else { 1.0 / b.m };
+
+    // Push the pair back out to the contact distance
+    let overlap = (contact - dist) / (inv_a + inv_b);
+    a.p -= normal * overlap * inv_a;
+    b.p += normal * overlap * inv_b;
+
+    let closing = (a.v - b.v).dot(normal);
+    if closing <= 0.0 {
+        return;
+    }
+    let impulse = 2.0 * closing / (inv_a + inv_b);
+    a.v -= normal * impulse * inv_a;
+    b.v += normal * impulse * inv_b;
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    fn with(encounter: Encounter) -> Interaction {
+        return Interaction {
+            encounter,
+            ..Interaction::default()
+        };
+    }
+
+    // Unit radius charges with ids matching their starting index
+    fn charges(list: &[(f32, Vec2, Vec2)]) -> Vec<Charge> {
+        return list
+            .iter()
+            .enumerate()
+            .map(|(i, &(q, p, v))| {
+                let mut c = Charge::new(q, 1.0, p, v, 1.0);
+                c.id = i as u32;
+                c
+            })
+            .collect();
+    }
+
+    #[test]
+    fn merge_keeps_every_other_charge() {
+        // Two touching pairs with a loner between them, removing from the middle of the list
+        // mustn't skip or repeat anyone
+        let mut list = charges(&[
+            (1.0, vec2(0.0, 0.0), vec2(1.0, 0.0)),
+            (2.0, vec2(0.5, 0.0), vec2(0.0, 1.0)),
+            (1.0, vec2(10.0, 0.0), Vec2::ZERO),
+            (-1.0, vec2(20.0, 0.0), Vec2::ZERO),
+            (3.0, vec2(20.0, 1.0), vec2(0.0, -2.0)),
+        ]);
+        with(Encounter::Merge).resolve(&mut list);
+
+        let ids: Vec<u32> = list.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [0, 2, 3]);
+        assert_eq!(list[0].q, 3.0);
+        assert_eq!(list[0].m, 2.0);
+        assert_eq!(list[0].v, vec2(0.5, 0.5));
+        assert_eq!(list[2].q, 2.0);
+        assert_eq!(list[2].p, vec2(20.0, 0.5));
+
+        // A chain all touching ends up as one charge
+        let mut list = charges(&[
+            (1.0, vec2(0.0, 0.0), Vec2::ZERO),
+            (1.0, vec2(1.5, 0.0), Vec2::ZERO),
+            (1.0, vec2(3.0, 0.0), Vec2::ZERO),
+        ]);
+        with(Encounter::Merge).resolve(&mut list);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].q, 3.0);
+    }
+
+    #[test]
+    fn annihilate_removes_opposite_pairs_only() {
+        let mut list = charges(&[
+            (1.0, vec2(0.0, 0.0), Vec2::ZERO),
+            (-1.0, vec2(0.5, 0.0), Vec2::ZERO),
+            (1.0, vec2(10.0, 0.0), Vec2::ZERO),
+            (1.0, vec2(10.5, 0.0), Vec2::ZERO),
+            (-1.0, vec2(20.0, 0.0), Vec2::ZERO),
+            (1.0, vec2(20.5, 0.0), Vec2::ZERO),
+            (1.0, vec2(30.0, 0.0), Vec2::ZERO),
+        ]);
+        with(Encounter::Annihilate).resolve(&mut list);
+
+        let ids: Vec<u32> = list.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [2, 3, 6]);
+    }
+
+    #[test]
+    fn bounce_off_a_pinned_charge() {
+        let mut list = charges(&[
+            (1.0, Vec2::ZERO, Vec2::ZERO),
+            (1.0, vec2(1.5, 0.0), vec2(-1.0, 0.5)),
+        ]);
+        list[0].set_fixed(true);
+        with(Encounter::Elastic).resolve(&mut list);
+
+        // The pin acts as a wall, so the free charge takes all of the push and the rebound
+        assert_eq!(list[0].p, Vec2::ZERO);
+        assert_eq!(list[0].v, Vec2::ZERO);
+        assert!(list[1].p.distance(vec2(2.0, 0.0)) < 1e-6);
+        assert!(list[1].v.distance(vec2(1.0, 0.5)) < 1e-6);
+
+        // Two pins are left where they are
+        let mut list = charges(&[
+            (1.0, Vec2::ZERO, Vec2::ZERO),
+            (1.0, vec2(1.5, 0.0), Vec2::ZERO),
+        ]);
+        list.iter_mut().for_each(|c| c.set_fixed(true));
+        with(Encounter::Elastic).resolve(&mut list);
+        assert_eq!(list[1].p, vec2(1.5, 0.0));
+    }
+}
@@ -0,0 +1,52 @@
+//! Charged particle and field simulation in the plane, without any rendering.
+//!
+//! [`charge::Charges`] holds a scene: the charges plus the interaction, magnetic, external
+//! field and boundary settings, and is what the save files deserialise into. Stepping it
+//! forward with an [`integrator::Integrator`] moves the charges, and
+//! [`charge::Charges::apply_to_field`] fills a [`field_grid::FieldGrid`] with E, B and the
+//! potential for plotting or analysis.
+//!
+//! ```no_run
+//! use vector_fields_core::{
+//!     barnes_hut::Solver, charge::Charges, field_grid::FieldGrid, integrator::Integrator,
+//! };
+//!
+//! # fn run(mut charges: Charges) {
+//! let solver = Solver { theta: 0.5, exact_below: 64, threads: 0 };
+//! let mut grid = FieldGrid::new([45, 25], 1);
+//! let bounds = grid.get_bounds();
+//!
+//! let dt = 0.02;
+//! for step in 0..1000 {
+//!     charges.step(Integrator::VelocityVerlet, step as f32 * dt, dt, bounds, solver);
+//!     charges.resolve_encounters();
+//!     charges.apply_boundary(bounds);
+//! }
+//! charges.apply_to_field(&mut grid, 1000.0 * dt, solver);
+//! # }
+//! ```
+//!
+//! Positions are in simulation units, bounds are `[min_x, max_x, min_y, max_y]` and grids
+//! are indexed `[y][x]` from the bottom row up. Enabling the `bevy` feature derives Bevy's
+//! `Resource` on the types the app keeps as resources.
+
+// Early and final returns are both spelled out across the codebase
+#![allow(clippy::needless_return)]
+
+pub mod barnes_hut;
+pub mod boundary;
+pub mod charge;
+pub mod clock;
+pub mod contours;
+pub mod diagnostics;
+pub mod external;
+pub mod field_grid;
+pub mod field_lines;
+pub mod grid_export;
+pub mod integrator;
+pub mod interaction;
+pub mod lic;
+pub mod magnetic;
+pub mod parallel;
+pub mod sampling;
+pub mod trajectory;
@@ -0,0 +1,91 @@
+//! Line integral convolution of a vector field
+
+use glam::{vec2, Vec2};
+
+use crate::{
+    parallel,
+    sampling::{Interpolation, OutOfBounds, Sampling},
+};
+
+/// Fewest texels worth handing a thread, callers sampling a field per texel split by it too
+pub const MIN_TEXELS_PER_THREAD: usize = 1024;
+
+// White noise that only depends on the texel and the seed, so the pattern holds still
+fn noise(x: usize, y: usize, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841) ^ seed;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    return h as f32 / u32::MAX as f32;
+}
+
+/// Averages the noise along the streamline through each texel of field, then stretches the
+/// result so +-2 standard deviations covers 0 to 1
+pub fn convolve(
+    field: &[Vec<Vec2>],
+    length: f32,
+    step: f32,
+    seed: u32,
+    threads: usize,
+) -> Vec<Vec<f32>> {
+    let height = field.len();
+    let width = field.first().map_or(0, |row| row.len());
+    let sampling = Sampling {
+        interpolation: Interpolation::Bilinear,
+        out_of_bounds: OutOfBounds::Reject,
+    };
+    let steps = (length / step).ceil() as usize;
+
+    let mut intensity = vec![0.0; width * height];
+    parallel::for_each_chunk(
+        &mut intensity,
+        threads,
+        MIN_TEXELS_PER_THREAD,
+        |start, chunk| {
+            for (i, value) in chunk.iter_mut().enumerate() {
+                let (x, y) = ((start + i) % width, (start + i) / width);
+
+                let mut sum = noise(x, y, seed);
+                let mut count = 1;
+                for sign in [1.0, -1.0] {
+                    let mut p = vec2(x as f32, y as f32);
+                    for _ in 0..steps {
+                        let Some(e) = sampling.sample(field, p) else {
+                            break;
+                        };
+                        let dir = e.normalize_or_zero();
+                        if dir == Vec2::ZERO {
+                            break;
+                        }
+                        p += dir * (sign * step);
+
+                        let (tx, ty) = (p.x.round(), p.y.round());
+                        if tx < 0.0 || ty < 0.0 || tx >= width as f32 || ty >= height as f32 {
+                            break;
+                        }
+                        sum += noise(tx as usize, ty as usize, seed);
+                        count += 1;
+                    }
+                }
+                *value = sum / count as f32;
+            }
+        },
+    );
+
+    let n = intensity.len().max(1) as f32;
+    let mean = intensity.iter().sum::<f32>() / n;
+    let std = (intensity.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n).sqrt();
+    let stretch = |v: f32| {
+        if std > 0.0 {
+            return (0.5 + (v - mean) / (4.0 * std)).clamp(0.0, 1.0);
+        }
+        return 0.5;
+    };
+
+    return intensity
+        .chunks(width.max(1))
+        .map(|row| row.iter().map(|v| stretch(*v)).collect())
+        .collect();
+}
@@ -0,0 +1,124 @@
+//! Magnetic fields, all out of the plane
+
+use std::f32::consts::PI;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Straight wire segment carrying a current from a to b
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Current {
+    /// Start of the wire
+    pub a: Vec2,
+    /// End of the wire
+    pub b: Vec2,
+    /// Current, negative runs from b to a
+    pub i: f32,
+}
+
+/// Everything lives in the plane so B only ever has a z component
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Magnetic {
+    /// Applied field everywhere, positive out of the screen
+    pub uniform: f32,
+    /// mu in the Biot-Savart law for currents and moving charges, 0 turns both off and
+    /// leaves only the uniform field
+    pub permeability: f32,
+    /// Wires carrying steady currents
+    pub currents: Vec<Current>,
+}
+impl Default for Magnetic {
+    fn default() -> Self {
+        Self {
+            uniform: 0.0,
+            permeability: 0.0,
+            currents: vec![],
+        }
+    }
+}
+impl Magnetic {
+    /// Applied B at a point, from the uniform field and the currents
+    pub fn calc_external_b(&self, at: Vec2) -> f32 {
+        let mut b = self.uniform;
+        if self.permeability == 0.0 {
+            return b;
+        }
+
+        for current in self.currents.iter() {
+            b += self.calc_segment_b(current, at);
+        }
+        return b;
+    }
+
+    /// Biot-Savart field of a point charge q at p moving with velocity v, qv = q * v
+    pub fn calc_point_b(&self, qv: Vec2, p: Vec2, at: Vec2, softening: f32) -> f32 {
+        if self.permeability == 0.0 {
+            return 0.0;
+        }
+
+        let r = at - p;
+        let dist_squared = r.length_squared() + softening * softening;
+        if dist_squared == 0.0 {
+            return 0.0;
+        }
+        return self.permeability / (4.0 * PI) * qv.perp_dot(r)
+            / (dist_squared * f32::sqrt(dist_squared));
+    }
+
+    fn calc_segment_b(&self, current: &Current, at: Vec2) -> f32 {
+        let seg = current.b - current.a;
+        let len = seg.length();
+        if len == 0.0 {
+            return 0.0;
+        }
+
+        let dir = seg / len;
+        let (to_a, to_b) = (at - current.a, at - current.b);
+        // Signed perpendicular distance from the line through the segment
+        let perp = dir.perp_dot(to_a);
+        if perp == 0.0 {
+            return 0.0;
+        }
+
+        let cos_a = dir.dot(to_a) / to_a.length();
+        let cos_b = dir.dot(to_b) / to_b.length();
+        return self.permeability / (4.0 * PI) * current.i * (cos_a - cos_b) / perp;
+    }
+}
+
+/// Magnetic part of the Lorentz force per unit charge, v x B with B along z
+pub fn calc_lorentz(v: Vec2, b: f32) -> Vec2 {
+    return Vec2::new(v.y * b, -v.x * b);
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+    use crate::integrator::Integrator;
+
+    #[test]
+    fn cyclotron_turns_clockwise_once_per_period() {
+        // Unit charge and mass in B = 2 out of the screen, r = m v / q B and T = 2 pi m / q B
+        let b = 2.0;
+        let (r, period) = (0.5, PI);
+        let acc = |_: &[Vec2], v: &[Vec2], _: f32| -> Vec<Vec2> {
+            return v.iter().map(|v| calc_lorentz(*v, b)).collect();
+        };
+
+        let steps = 1000;
+        let dt = period / steps as f32;
+        let (mut p, mut v) = (vec![Vec2::ZERO], vec![vec2(1.0, 0.0)]);
+        for step in 1..=steps {
+            Integrator::RK4.step(&mut p, &mut v, dt, acc);
+            // A quarter turn clockwise from heading along +x about (0, -r)
+            if step == steps / 4 {
+                assert!(p[0].distance(vec2(r, -r)) < 1e-3, "{:?}", p[0]);
+                assert!(v[0].distance(vec2(0.0, -1.0)) < 1e-3, "{:?}", v[0]);
+            }
+        }
+        assert!(p[0].length() < 1e-3, "{:?}", p[0]);
+        assert!(v[0].distance(vec2(1.0, 0.0)) < 1e-3, "{:?}", v[0]);
+    }
+}
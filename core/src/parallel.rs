@@ -0,0 +1,80 @@
+//! Splitting work across threads, or Bevy's compute task pool with the bevy feature
+
+use std::thread;
+
+#[cfg(feature = "bevy")]
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+/// 0 means use every core
+pub fn thread_count(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    return thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+}
+
+/// Splits items into contiguous chunks of at least min_chunk items and runs f on each
+/// chunk in parallel, f gets the index of the first item in its chunk
+pub fn for_each_chunk<T, F>(items: &mut [T], threads: usize, min_chunk: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let max_threads = items.len().div_ceil(min_chunk.max(1));
+    let threads = thread_count(threads).min(max_threads);
+    if threads <= 1 {
+        f(0, items);
+        return;
+    }
+
+    let chunk_size = items.len().div_ceil(threads);
+    run_chunks(items, chunk_size, f);
+}
+
+#[cfg(feature = "bevy")]
+fn run_chunks<T, F>(items: &mut [T], chunk_size: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    // The app's TaskPoolPlugin sets the pool up, headless runs and tests start a default one
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    pool.scope(|scope| {
+        for (i, chunk) in items.chunks_mut(chunk_size).enumerate() {
+            let f = &f;
+            scope.spawn(async move { f(i * chunk_size, chunk) });
+        }
+    });
+}
+
+#[cfg(not(feature = "bevy"))]
+fn run_chunks<T, F>(items: &mut [T], chunk_size: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    thread::scope(|scope| {
+        for (i, chunk) in items.chunks_mut(chunk_size).enumerate() {
+            let f = &f;
+            scope.spawn(move || f(i * chunk_size, chunk));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_item_sees_its_own_index() {
+        let mut items = vec![0; 1000];
+        for_each_chunk(&mut items, 4, 10, |start, chunk| {
+            for (i, item) in chunk.iter_mut().enumerate() {
+                *item += start + i;
+            }
+        });
+        assert!(items.iter().enumerate().all(|(i, item)| *item == i));
+    }
+}
@@ -0,0 +1,179 @@
+//! Interpolated reads from grids
+
+use std::ops::{Add, Mul};
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// How values between cell centres are found
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Interpolation {
+    /// The value of the cell the point is in
+    Nearest,
+    /// Linear in each direction between the 4 nearest cells
+    Bilinear,
+    /// Catmull-Rom over the surrounding 4x4 cells
+    Bicubic,
+}
+
+/// What sampling outside the grid gives
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum OutOfBounds {
+    /// Use the value at the nearest edge
+    Clamp,
+    /// Treat the grid as tiling the plane
+    Wrap,
+    /// Zero, as if the grid were padded with it
+    Zero,
+    /// Sampling outside the grid gives None
+    Reject,
+}
+
+/// How grids are read between and beyond their cells
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Sampling {
+    /// Between cells
+    pub interpolation: Interpolation,
+    /// Beyond the edges
+    pub out_of_bounds: OutOfBounds,
+}
+impl Default for Sampling {
+    fn default() -> Self {
+        Self {
+            interpolation: Interpolation::Bilinear,
+            out_of_bounds: OutOfBounds::Clamp,
+        }
+    }
+}
+impl Sampling {
+    /// pos is in grid cells, so `grid[y][x]` sits at (x, y)
+    pub fn sample<T>(&self, grid: &[Vec<T>], pos: Vec2) -> Option<T>
+    where
+        T: Copy + Default + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let height = grid.len();
+        if height == 0 || grid[0].is_empty() {
+            return None;
+        }
+        let width = grid[0].len();
+
+        let (max_x, max_y) = ((width - 1) as f32, (height - 1) as f32);
+        let outside = pos.x < 0.0 || pos.x > max_x || pos.y < 0.0 || pos.y > max_y;
+        if outside {
+            match self.out_of_bounds {
+                OutOfBounds::Zero => return Some(T::default()),
+                OutOfBounds::Reject => return None,
+                OutOfBounds::Clamp | OutOfBounds::Wrap => {}
+            }
+        }
+
+        let wrap = matches!(self.out_of_bounds, OutOfBounds::Wrap);
+        let fetch = |x: i64, y: i64| -> T {
+            let (x, y) = if wrap {
+                (x.rem_euclid(width as i64), y.rem_euclid(height as i64))
+            } else {
+                (x.clamp(0, width as i64 - 1), y.clamp(0, height as i64 - 1))
+            };
+            return grid[y as usize][x as usize];
+        };
+
+        let (x0, y0) = (pos.x.floor() as i64, pos.y.floor() as i64);
+        let (tx, ty) = (pos.x - x0 as f32, pos.y - y0 as f32);
+        let value = match self.interpolation {
+            Interpolation::Nearest => fetch(pos.x.round() as i64, pos.y.round() as i64),
+            Interpolation::Bilinear => {
+                let bottom = fetch(x0, y0) * (1.0 - tx) + fetch(x0 + 1, y0) * tx;
+                let top = fetch(x0, y0 + 1) * (1.0 - tx) + fetch(x0 + 1, y0 + 1) * tx;
+                bottom * (1.0 - ty) + top * ty
+            }
+            Interpolation::Bicubic => {
+                let (wx, wy) = (catmull_rom(tx), catmull_rom(ty));
+                let mut total = T::default();
+                for (j, w_j) in wy.iter().enumerate() {
+                    let mut row = T::default();
+                    for (i, w_i) in wx.iter().enumerate() {
+                        row = row + fetch(x0 + i as i64 - 1, y0 + j as i64 - 1) * *w_i;
+                    }
+                    total = total + row * *w_j;
+                }
+                total
+            }
+        };
+        return Some(value);
+    }
+}
+
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    return [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    fn linear(p: Vec2) -> f32 {
+        return 2.0 * p.x - 3.0 * p.y + 1.0;
+    }
+
+    // 8x6 grid holding the linear function at each cell
+    fn grid() -> Vec<Vec<f32>> {
+        return (0..6)
+            .map(|y| (0..8).map(|x| linear(vec2(x as f32, y as f32))).collect())
+            .collect();
+    }
+
+    fn sampling(interpolation: Interpolation) -> Sampling {
+        return Sampling {
+            interpolation,
+            out_of_bounds: OutOfBounds::Reject,
+        };
+    }
+
+    #[test]
+    fn bilinear_is_exact_on_a_linear_field() {
+        let grid = grid();
+        let sampling = sampling(Interpolation::Bilinear);
+        for p in [
+            vec2(0.0, 0.0),
+            vec2(3.25, 1.5),
+            vec2(6.9, 4.1),
+            vec2(7.0, 5.0),
+        ] {
+            let value = sampling.sample(&grid, p).unwrap();
+            assert!((value - linear(p)).abs() < 1e-4, "{} at {}", value, p);
+        }
+    }
+
+    #[test]
+    fn bicubic_is_exact_on_a_linear_field() {
+        // Away from the edges, where clamping would repeat the border cells
+        let grid = grid();
+        let sampling = sampling(Interpolation::Bicubic);
+        for p in [
+            vec2(1.0, 1.0),
+            vec2(3.25, 1.5),
+            vec2(5.9, 3.1),
+            vec2(2.5, 4.0),
+        ] {
+            let value = sampling.sample(&grid, p).unwrap();
+            assert!((value - linear(p)).abs() < 1e-4, "{} at {}", value, p);
+        }
+    }
+
+    #[test]
+    fn reject_only_refuses_points_outside() {
+        let grid = grid();
+        let sampling = sampling(Interpolation::Bilinear);
+        assert!(sampling.sample(&grid, vec2(7.0, 5.0)).is_some());
+        assert!(sampling.sample(&grid, vec2(7.01, 2.0)).is_none());
+        assert!(sampling.sample(&grid, vec2(1.0, -0.01)).is_none());
+    }
+}
@@ -0,0 +1,382 @@
+//! Recorded charge histories and the files they are exported to
+//!
+//! The columnar format is a small header followed by each column stored contiguously,
+//! all little endian:
+//!
+//! ```text
+//! magic    8 bytes    "VFTRAJ01"
+//! rows     u32
+//! columns  u32
+//! per column:
+//!   name   u8 length then that many bytes of UTF-8
+//!   type   u8, b'u' for u32 or b'f' for f32
+//! per column, in header order:
+//!   data   rows values of the column's type
+//! ```
+//!
+//! Every value is 4 bytes wide, so column `i` starts `4 * rows * i` bytes after the header
+//! and can be read straight into an array, e.g. with `numpy.frombuffer`.
+//!
+//! [`CsvWriter`] and [`ColumnarWriter`] write rows as the simulation produces them, so a long
+//! run never has to hold its whole history in memory.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use glam::{vec2, Vec2};
+
+use crate::{charge::Charge, clock::SimulationClock};
+
+const MAGIC: &[u8; 8] = b"VFTRAJ01";
+// Names and types, in the order rows() gives the values
+const COLUMNS: [(&str, u8); 11] = [
+    ("step", b'u'),
+    ("time", b'f'),
+    ("id", b'u'),
+    ("q", b'f'),
+    ("m", b'f'),
+    ("x", b'f'),
+    ("y", b'f'),
+    ("vx", b'f'),
+    ("vy", b'f'),
+    ("ax", b'f'),
+    ("ay", b'f'),
+];
+
+enum Column {
+    U32(Vec<u32>),
+    F32(Vec<f32>),
+}
+
+// A single value of a row, in the type its column is stored as
+#[derive(Copy, Clone)]
+enum Cell {
+    U32(u32),
+    F32(f32),
+}
+impl Cell {
+    fn to_le_bytes(self) -> [u8; 4] {
+        return match self {
+            Cell::U32(value) => value.to_le_bytes(),
+            Cell::F32(value) => value.to_le_bytes(),
+        };
+    }
+}
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Cell::U32(value) => value.fmt(f),
+            Cell::F32(value) => value.fmt(f),
+        };
+    }
+}
+
+// Values for COLUMNS, one row per charge
+fn rows<'a>(
+    charges: &'a [Charge],
+    clock: &'a SimulationClock,
+    a: &'a [Vec2],
+) -> impl Iterator<Item = [Cell; 11]> + 'a {
+    return charges.iter().zip(a).map(|(c, a)| {
+        [
+            Cell::U32(clock.steps as u32),
+            Cell::F32(clock.elapsed),
+            Cell::U32(c.id),
+            Cell::F32(c.q),
+            Cell::F32(c.m),
+            Cell::F32(c.p.x),
+            Cell::F32(c.p.y),
+            Cell::F32(c.v.x),
+            Cell::F32(c.v.y),
+            Cell::F32(a.x),
+            Cell::F32(a.y),
+        ]
+    });
+}
+
+/// Comma separated with a header row, one line per row
+pub struct CsvWriter<W: Write> {
+    out: W,
+}
+impl<W: Write> CsvWriter<W> {
+    /// Writes the header row to out
+    pub fn new(mut out: W) -> io::Result<Self> {
+        let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        writeln!(out, "{}", names.join(","))?;
+        return Ok(Self { out });
+    }
+
+    /// Appends a row for every charge, a holds their accelerations in the same order
+    pub fn write(
+        &mut self,
+        charges: &[Charge],
+        clock: &SimulationClock,
+        a: &[Vec2],
+    ) -> io::Result<()> {
+        for row in rows(charges, clock, a) {
+            let values: Vec<String> = row.iter().map(|cell| cell.to_string()).collect();
+            writeln!(self.out, "{}", values.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and hands back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        return Ok(self.out);
+    }
+}
+
+/// The columnar format described in the module docs
+///
+/// Since every column is stored whole, each one goes to its own temporary file next to path
+/// until [`ColumnarWriter::finish`] joins them behind the header
+pub struct ColumnarWriter {
+    path: PathBuf,
+    parts: Vec<(PathBuf, BufWriter<File>)>,
+    rows: u32,
+}
+impl ColumnarWriter {
+    /// Starts a file at path, nothing is written there until finish
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut parts = Vec::with_capacity(COLUMNS.len());
+        for (name, _) in COLUMNS {
+            let mut part = path.as_os_str().to_owned();
+            part.push(format!(".{}.tmp", name));
+            let part = PathBuf::from(part);
+            let file = BufWriter::new(File::create(&part)?);
+            parts.push((part, file));
+        }
+        return Ok(Self {
+            path: path.to_path_buf(),
+            parts,
+            rows: 0,
+        });
+    }
+
+    /// Appends a row for every charge, a holds their accelerations in the same order
+    pub fn write(
+        &mut self,
+        charges: &[Charge],
+        clock: &SimulationClock,
+        a: &[Vec2],
+    ) -> io::Result<()> {
+        for row in rows(charges, clock, a) {
+            for (cell, (_, part)) in row.iter().zip(self.parts.iter_mut()) {
+                part.write_all(&cell.to_le_bytes())?;
+            }
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the file at path and removes the temporary ones, returns the number of rows
+    pub fn finish(self) -> io::Result<u32> {
+        let mut out = BufWriter::new(File::create(&self.path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&self.rows.to_le_bytes())?;
+        out.write_all(&(COLUMNS.len() as u32).to_le_bytes())?;
+        for (name, kind) in COLUMNS {
+            out.write_all(&[name.len() as u8])?;
+            out.write_all(name.as_bytes())?;
+            out.write_all(&[kind])?;
+        }
+
+        for (part, file) in self.parts {
+            // Flushes the part and closes it before reading it back
+            file.into_inner().map_err(|e| e.into_error())?;
+            io::copy(&mut BufReader::new(File::open(&part)?), &mut out)?;
+            fs::remove_file(&part)?;
+        }
+        out.flush()?;
+        return Ok(self.rows);
+    }
+}
+
+/// A columnar file read back into memory, one entry per charge per recorded step
+///
+/// Charges are identified by [`Charge::id`], which survives other charges being removed, and
+/// a merged charge keeps the id of the one listed first
+#[derive(Clone, Default)]
+pub struct Trajectory {
+    /// Step the row was recorded at
+    pub step: Vec<u32>,
+    /// Simulated time the row was recorded at
+    pub time: Vec<f32>,
+    /// Which charge the row is for
+    pub id: Vec<u32>,
+    /// Charge
+    pub q: Vec<f32>,
+    /// Mass
+    pub m: Vec<f32>,
+    /// Position
+    pub p: Vec<Vec2>,
+    /// Velocity
+    pub v: Vec<Vec2>,
+    /// Acceleration at the start of the step
+    pub a: Vec<Vec2>,
+}
+impl Trajectory {
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        return self.step.len();
+    }
+
+    /// Whether there are no rows
+    pub fn is_empty(&self) -> bool {
+        return self.step.is_empty();
+    }
+
+    /// Reads back a file written by [`ColumnarWriter`]
+    pub fn read_columnar<R: Read>(input: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a trajectory file"));
+        }
+        let rows = read_u32(input)? as usize;
+        let num_columns = read_u32(input)? as usize;
+
+        let mut header = Vec::with_capacity(num_columns);
+        for _ in 0..num_columns {
+            let mut len = [0; 1];
+            input.read_exact(&mut len)?;
+            let mut name = vec![0; len[0] as usize];
+            input.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid("column name isn't UTF-8"))?;
+            let mut kind = [0; 1];
+            input.read_exact(&mut kind)?;
+            header.push((name, kind[0]));
+        }
+
+        let mut columns = Vec::with_capacity(num_columns);
+        for (name, kind) in header {
+            let column = match kind {
+                b'u' => Column::U32(
+                    (0..rows)
+                        .map(|_| read_u32(input))
+                        .collect::<io::Result<_>>()?,
+                ),
+                b'f' => Column::F32(
+                    (0..rows)
+                        .map(|_| read_f32(input))
+                        .collect::<io::Result<_>>()?,
+                ),
+                _ => return Err(invalid("unknown column type")),
+            };
+            columns.push((name, column));
+        }
+
+        // Columns are looked up by name so files with extra or reordered columns still load
+        let u32_column = |name: &str| -> io::Result<Vec<u32>> {
+            return match columns.iter().find(|(n, _)| n == name) {
+                Some((_, Column::U32(values))) => Ok(values.clone()),
+                _ => Err(invalid(&format!("missing u32 column {}", name))),
+            };
+        };
+        let f32_column = |name: &str| -> io::Result<Vec<f32>> {
+            return match columns.iter().find(|(n, _)| n == name) {
+                Some((_, Column::F32(values))) => Ok(values.clone()),
+                _ => Err(invalid(&format!("missing f32 column {}", name))),
+            };
+        };
+        let vec2_column = |x: &str, y: &str| -> io::Result<Vec<Vec2>> {
+            let (x, y) = (f32_column(x)?, f32_column(y)?);
+            return Ok(x.into_iter().zip(y).map(|(x, y)| vec2(x, y)).collect());
+        };
+
+        return Ok(Self {
+            step: u32_column("step")?,
+            time: f32_column("time")?,
+            id: u32_column("id")?,
+            q: f32_column("q")?,
+            m: f32_column("m")?,
+            p: vec2_column("x", "y")?,
+            v: vec2_column("vx", "vy")?,
+            a: vec2_column("ax", "ay")?,
+        });
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    return Ok(u32::from_le_bytes(bytes));
+}
+
+fn read_f32<R: Read>(input: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    return Ok(f32::from_le_bytes(bytes));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charges() -> Vec<Charge> {
+        let mut a = Charge::new(1.0, 2.0, vec2(0.5, -1.5), vec2(0.25, 0.0), 0.0);
+        a.id = 3;
+        let mut b = Charge::new(-1.0, 1.0, vec2(-2.0, 4.0), vec2(0.0, -0.75), 0.0);
+        b.id = 7;
+        return vec![a, b];
+    }
+
+    #[test]
+    fn columnar_round_trip() {
+        let path = std::env::temp_dir().join(format!("trajectory_{}.vft", std::process::id()));
+        let charges = charges();
+        let a = [vec2(0.1, 0.2), vec2(-0.3, 0.4)];
+        let mut clock = SimulationClock::default();
+
+        let mut writer = ColumnarWriter::create(&path).unwrap();
+        writer.write(&charges, &clock, &a).unwrap();
+        clock.tick(0.5);
+        writer.write(&charges, &clock, &a).unwrap();
+        assert_eq!(writer.finish().unwrap(), 4);
+
+        let trajectory = Trajectory::read_columnar(&mut File::open(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(trajectory.len(), 4);
+        assert_eq!(trajectory.step, [0, 0, 1, 1]);
+        assert_eq!(trajectory.time, [0.0, 0.0, 0.5, 0.5]);
+        assert_eq!(trajectory.id, [3, 7, 3, 7]);
+        assert_eq!(trajectory.q, [1.0, -1.0, 1.0, -1.0]);
+        assert_eq!(trajectory.m, [2.0, 1.0, 2.0, 1.0]);
+        for (i, c) in charges.iter().cycle().take(4).enumerate() {
+            assert_eq!(trajectory.p[i], c.p);
+            assert_eq!(trajectory.v[i], c.v);
+            assert_eq!(trajectory.a[i], a[i % 2]);
+        }
+
+        // Nothing is left next to the file
+        let dir = fs::read_dir(path.parent().unwrap()).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(dir
+            .flatten()
+            .all(|entry| !entry.file_name().to_string_lossy().starts_with(&name)));
+    }
+
+    #[test]
+    fn csv_has_a_line_per_charge() {
+        let mut writer = CsvWriter::new(vec![]).unwrap();
+        let a = [Vec2::ZERO, vec2(-0.5, 1.0)];
+        writer
+            .write(&charges(), &SimulationClock::default(), &a)
+            .unwrap();
+        let csv = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "step,time,id,q,m,x,y,vx,vy,ax,ay");
+        assert_eq!(lines[2], "0,0,7,-1,1,-2,4,0,-0.75,-0.5,1");
+        assert_eq!(lines.len(), 3);
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::{AssetServer, Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    math::Vec2,
+    prelude::default,
+    render::{
+        color::Color,
+        mesh::{Indices, Mesh},
+        render_resource::PrimitiveTopology,
+    },
+    sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{colormap::ColorRanges, setting::Settings, vector_field::VectorField};
+
+// Corners of an arrow quad and where they sit on the texture, whose rows run top to bottom
+const CORNERS: [(Vec2, [f32; 2]); 4] = [
+    (Vec2::new(-0.5, -0.5), [0.0, 1.0]),
+    (Vec2::new(0.5, -0.5), [1.0, 1.0]),
+    (Vec2::new(0.5, 0.5), [1.0, 0.0]),
+    (Vec2::new(-0.5, 0.5), [0.0, 0.0]),
+];
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum ArrowRenderer {
+    // One sprite entity per cell
+    Sprites,
+    // Every arrow written into a single mesh
+    Batched,
+    // No arrows, for when the LIC texture shows the field instead
+    None,
+}
+
+#[derive(Resource)]
+pub struct ArrowMesh {
+    mesh: Handle<Mesh>,
+}
+
+pub struct ArrowPlugin;
+impl Plugin for ArrowPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(PostStartup, spawn_arrow_mesh)
+            .add_systems(Update, update_arrow_mesh);
+    }
+}
+
+fn spawn_arrow_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    let vector = &settings.simulation.vector;
+    if !matches!(vector.renderer, ArrowRenderer::Batched) {
+        return;
+    }
+
+    // The layout never changes, only positions and colours are rewritten each update
+    let [width, height] = vector_field.get_shape();
+    let quads = width * height;
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(quads * 4);
+    let mut indices: Vec<u32> = Vec::with_capacity(quads * 6);
+    for i in 0..quads as u32 {
+        uvs.extend(CORNERS.iter().map(|(_, uv)| *uv));
+        indices.extend([0, 1, 2, 0, 2, 3].map(|c| i * 4 + c));
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; quads * 4]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[0.0f32; 4]; quads * 4]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    let mesh = meshes.add(mesh);
+
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(mesh.clone()),
+        material: materials.add(ColorMaterial {
+            color: Color::WHITE,
+            texture: Some(asset_server.load(vector.texture.clone())),
+        }),
+        ..default()
+    });
+    commands.insert_resource(ArrowMesh { mesh });
+}
+
+fn update_arrow_mesh(
+    mut meshes: ResMut<Assets<Mesh>>,
+    arrow_mesh: Option<Res<ArrowMesh>>,
+    vector_field: Res<VectorField>,
+    mut color_ranges: ResMut<ColorRanges>,
+    settings: Res<Settings>,
+) {
+    let Some(arrow_mesh) = arrow_mesh else {
+        return;
+    };
+    if !vector_field.is_changed() {
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&arrow_mesh.mesh) else {
+        return;
+    };
+
+    let simulation = &settings.simulation;
+    let [width, height] = vector_field.get_shape();
+    let view = simulation.field.view;
+    let colors = &view.arrow_colors(&simulation.colors.arrows);
+    let range = colors.range(&vector_field.color_values(view));
+    color_ranges.arrows = range;
+    let arrows = vector_field.arrows(view, simulation.vector.encoding, colors, range);
+
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(width * height * 4);
+    let mut colors: Vec<[f32; 4]> = Vec::with_capacity(width * height * 4);
+    let cells = arrows
+        .iter()
+        .flatten()
+        .zip(vector_field.coords.iter().flatten());
+    for (arrow, coord) in cells {
+        let centre = *coord * simulation.scale;
+        let size = simulation.vector.size * arrow.scale;
+
+        for (corner, _) in CORNERS.iter() {
+            let offset = arrow.rotation * (*corner * size).extend(0.0);
+            positions.push((centre.extend(0.0) + offset).to_array());
+            colors.push(arrow.color.as_linear_rgba_f32());
+        }
+    }
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+}
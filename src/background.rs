@@ -0,0 +1,119 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::{Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    math::{vec2, vec3},
+    prelude::default,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{Image, ImageSampler},
+    },
+    sprite::{Sprite, SpriteBundle},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{colormap::ColorRanges, setting::Settings, vector_field::VectorField};
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Background {
+    None,
+    Potential,
+    Divergence,
+    Curl,
+}
+
+#[derive(Resource)]
+pub struct BackgroundImage {
+    image: Handle<Image>,
+}
+
+pub struct BackgroundPlugin;
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        // The vector field is only laid out during Startup
+        app.add_systems(PostStartup, spawn_background)
+            .add_systems(Update, update_background);
+    }
+}
+
+fn spawn_background(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    if matches!(settings.simulation.field.background, Background::None) {
+        return;
+    }
+
+    let [width, height] = vector_field.get_shape();
+    let mut image = Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        vec![0; width * height * 4],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::linear();
+    let image = images.add(image);
+
+    // One texel per grid cell, centred on the arrow it sits under
+    let cell = settings.simulation.scale / vector_field.get_resolution() as f32;
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(vec2(width as f32, height as f32) * cell),
+            ..default()
+        },
+        texture: image.clone(),
+        transform: Transform::from_translation(vec3(0.0, 0.0, -1.0)),
+        ..default()
+    });
+    commands.insert_resource(BackgroundImage { image });
+}
+
+fn update_background(
+    mut images: ResMut<Assets<Image>>,
+    background_image: Option<Res<BackgroundImage>>,
+    vector_field: Res<VectorField>,
+    mut color_ranges: ResMut<ColorRanges>,
+    settings: Res<Settings>,
+) {
+    let Some(background_image) = background_image else {
+        return;
+    };
+    if !vector_field.is_changed() {
+        return;
+    }
+    let Some(image) = images.get_mut(&background_image.image) else {
+        return;
+    };
+
+    let values = match settings.simulation.field.background {
+        Background::None => return,
+        Background::Potential => &vector_field.potential,
+        Background::Divergence => &vector_field.divergence,
+        Background::Curl => &vector_field.curl,
+    };
+
+    let [width, height] = vector_field.get_shape();
+    let colors = &settings.simulation.colors.background;
+    let range = colors.range(values);
+    color_ranges.background = range;
+
+    // Texture rows run top to bottom while the grid runs bottom to top
+    for (y, values_row) in values.iter().enumerate() {
+        let row = height - 1 - y;
+        for (x, value) in values_row.iter().enumerate() {
+            let i = (row * width + x) * 4;
+            let color = colors.color(*value, range);
+            image.data[i..i + 4].copy_from_slice(&color.as_rgba_u8());
+        }
+    }
+}
@@ -1,13 +1,10 @@
+use std::collections::HashMap;
+
 use super::{
     icons::{ArrowIcon, ChargeIcon, IconBuilders},
     ui_elements::{ButtonBuilder, ButtonGroup, ButtonGroupBuilder},
 };
-use crate::{
-    charge::{Charge, Charges},
-    controls::state::ControlState,
-    setting::Settings,
-    utils,
-};
+use crate::{controls::state::ControlState, setting::Settings, utils};
 use bevy::{
     ecs::{
         change_detection::DetectChanges,
@@ -16,12 +13,18 @@ use bevy::{
         query::{Changed, With, Without},
         system::{Commands, Query, Res, ResMut, Resource},
     },
-    hierarchy::{BuildChildren, Children},
+    hierarchy::{BuildChildren, Children, DespawnRecursiveExt},
     math::vec2,
     prelude::default,
+    render::color::Color,
+    sprite::Sprite,
     transform::components::Transform,
     ui::{node_bundles::NodeBundle, JustifyContent, Style, Val},
 };
+use vector_fields_core::charge::{Charge, Charges};
+
+const FREE_CHARGE_COLOR: Color = Color::WHITE;
+const PINNED_CHARGE_COLOR: Color = Color::GRAY;
 
 pub enum Mode {
     None,
@@ -29,21 +32,22 @@ pub enum Mode {
     Move,
     Velocity,
     Charge,
+    Pin,
 }
 #[derive(Resource)]
 pub struct EditorState {
     mode: Mode,
-    cur_charge_index: Option<usize>,
-    charge_icons: Vec<Entity>,
-    arrow_icons: Vec<Entity>,
+    // Keyed by Charge::id rather than list position, which shifts when charges are removed
+    cur_charge_id: Option<u32>,
+    // Each charge's icon and the velocity arrow under it
+    icons: HashMap<u32, (Entity, Entity)>,
 }
 impl EditorState {
     pub fn new() -> Self {
         Self {
             mode: Mode::None,
-            cur_charge_index: None,
-            charge_icons: vec![],
-            arrow_icons: vec![],
+            cur_charge_id: None,
+            icons: HashMap::new(),
         }
     }
 }
@@ -54,7 +58,7 @@ pub struct ButtonGroupTag;
 pub struct PauseButtonTag;
 
 pub fn spawn_ui(mut commands: Commands) {
-    let (button_width, button_height) = (24.5, 95.0);
+    let (button_width, button_height) = (19.5, 95.0);
     let button_builder = ButtonBuilder::new(None, None, None, button_width, button_height);
 
     let (group_width, group_height) = (100.0, 20.0);
@@ -64,6 +68,7 @@ pub fn spawn_ui(mut commands: Commands) {
             ["Move charges".into(), "Moving Charges".into()],
             ["Alter velocities".into(), "Altering velocities".into()],
             ["Alter charge".into(), "Altering Charges".into()],
+            ["Pin charges".into(), "Pinning Charges".into()],
         ],
         group_width,
         group_height,
@@ -109,6 +114,8 @@ pub fn update_editor_mode(
                 mode = Mode::Velocity
             } else if cur_mode_id == 3 {
                 mode = Mode::Charge
+            } else if cur_mode_id == 4 {
+                mode = Mode::Pin
             }
 
             editor_state.mode = mode;
@@ -116,21 +123,68 @@ pub fn update_editor_mode(
     }
 }
 
-pub fn update_icons(charges: Res<Charges>) {
+type ChargeIconQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Transform, &'static mut Sprite),
+    (With<ChargeIcon>, Without<ArrowIcon>),
+>;
+
+pub fn update_icons(
+    mut commands: Commands,
+    mut editor_state: ResMut<EditorState>,
+    mut charge_icons: ChargeIconQuery,
+    mut arrow_icons: Query<&mut Transform, (With<ArrowIcon>, Without<ChargeIcon>)>,
+    charges: Res<Charges>,
+    builders: Res<IconBuilders>,
+    settings: Res<Settings>,
+) {
     if !charges.is_changed() {
         return;
     }
+
+    // Merges, annihilations and absorbing walls remove charges, a velocity drag can't
+    // carry over to whichever charge is left
+    if editor_state.icons.len() != charges.charges.len() {
+        editor_state.cur_charge_id = None;
+    }
+
+    editor_state.icons.retain(|id, (charge_ent, _)| {
+        let alive = charges.charges.iter().any(|c| c.id == *id);
+        if !alive {
+            commands.entity(*charge_ent).despawn_recursive();
+        }
+        alive
+    });
+
+    let scale = settings.simulation.scale;
+    for c in charges.charges.iter() {
+        let (charge_ent, arrow_ent) = *editor_state
+            .icons
+            .entry(c.id)
+            .or_insert_with(|| builders.build_charge(&mut commands, c.p * scale, c.id));
+        if let Ok((mut transform, mut sprite)) = charge_icons.get_mut(charge_ent) {
+            let z = transform.translation.z;
+            transform.translation = (c.p * scale).extend(z);
+            sprite.color = if c.fixed {
+                PINNED_CHARGE_COLOR
+            } else {
+                FREE_CHARGE_COLOR
+            };
+        }
+        if let Ok(mut transform) = arrow_icons.get_mut(arrow_ent) {
+            transform.scale.x = c.v.length();
+            transform.rotation = utils::dir_to_quat(c.v);
+        }
+    }
 }
 
 pub fn if_create_charge(editor_state: Res<EditorState>) -> bool {
     return matches!(editor_state.mode, Mode::Create);
 }
 pub fn create_charge(
-    mut commands: Commands,
     mut control_state: ResMut<ControlState>,
     mut charges: ResMut<Charges>,
-    mut editor_state: ResMut<EditorState>,
-    builders: Res<IconBuilders>,
     settings: Res<Settings>,
 ) {
     if !control_state.double_click {
@@ -138,19 +192,12 @@ pub fn create_charge(
     }
     control_state.double_click = false;
 
-    let id = charges.charges.len();
     let world_pos = control_state.mouse_world_pos;
     let pos = world_pos / settings.simulation.scale;
+    let radius = settings.icons.charge_size / settings.simulation.scale / 2.0;
 
-    let (charge_ent, arrow_ent) =
-        builders.build_charge(&mut commands, control_state.mouse_world_pos, id);
-
-    editor_state.charge_icons.insert(id, charge_ent);
-    editor_state.arrow_icons.insert(id, arrow_ent);
-
-    charges
-        .charges
-        .insert(id, Charge::new(1.0, 1.0, pos, vec2(0.0, 0.0)));
+    // update_icons gives it an icon next frame
+    charges.add(Charge::new(1.0, 1.0, pos, vec2(0.0, 0.0), radius));
 }
 
 pub fn if_move_charge(editor_state: Res<EditorState>) -> bool {
@@ -173,9 +220,12 @@ pub fn move_charge(
             continue;
         }
 
+        let Some(charge) = charges.charges.iter_mut().find(|c| c.id == icon.id) else {
+            continue;
+        };
         let z = transform.translation.z;
         transform.translation = mouse_world_pos.extend(z);
-        charges.charges[icon.id].p = mouse_world_pos / settings.simulation.scale;
+        charge.p = mouse_world_pos / settings.simulation.scale;
         return;
     }
 }
@@ -192,30 +242,36 @@ pub fn edit_velocity(
     settings: Res<Settings>,
 ) {
     if control_state.left_mouse_just_down {
-        editor_state.cur_charge_index = None;
+        editor_state.cur_charge_id = None;
         for (transform, charge_icon) in charge_icons.iter() {
             let dir = control_state.mouse_world_pos - transform.translation.truncate();
             if dir.length_squared() > settings.icons.charge_size.powi(2) {
                 continue;
             }
 
-            editor_state.cur_charge_index = Some(charge_icon.id);
+            editor_state.cur_charge_id = Some(charge_icon.id);
         }
     }
 
     if control_state.left_mouse_down {
-        if let Some(i) = editor_state.cur_charge_index {
-            let charge_pos = match charge_icons.get(editor_state.charge_icons[i]) {
+        if let Some(id) = editor_state.cur_charge_id {
+            let Some(&(charge_ent, arrow_ent)) = editor_state.icons.get(&id) else {
+                return;
+            };
+            let Some(charge) = charges.charges.iter_mut().find(|c| c.id == id) else {
+                return;
+            };
+            let charge_pos = match charge_icons.get(charge_ent) {
                 Ok((transform, _)) => transform.translation.truncate(),
                 Err(err) => panic!("Charge not found when trying to edit velocity, {:?}", err),
             };
 
             let vel = (control_state.mouse_world_pos - charge_pos) / settings.simulation.scale;
-            match arrow_icons.get_mut(editor_state.arrow_icons[i]) {
+            match arrow_icons.get_mut(arrow_ent) {
                 Ok(mut transform) => {
                     transform.scale.x = vel.length();
                     transform.rotation = utils::dir_to_quat(vel);
-                    charges.charges[i].v = vel;
+                    charge.v = vel;
                 }
                 Err(err) => panic!(
                     "Velocity arrow not found when trying to edit velocity, {:?}",
@@ -230,3 +286,31 @@ pub fn if_edit_charge(editor_state: Res<EditorState>) -> bool {
     return matches!(editor_state.mode, Mode::Charge);
 }
 pub fn edit_charge() {}
+
+pub fn if_pin_charge(editor_state: Res<EditorState>) -> bool {
+    return matches!(editor_state.mode, Mode::Pin);
+}
+pub fn pin_charge(
+    charge_icons: Query<(&Transform, &ChargeIcon)>,
+    mut charges: ResMut<Charges>,
+    control_state: Res<ControlState>,
+    settings: Res<Settings>,
+) {
+    if !control_state.left_mouse_just_down {
+        return;
+    }
+
+    for (transform, charge_icon) in charge_icons.iter() {
+        let dir = control_state.mouse_world_pos - transform.translation.truncate();
+        if dir.length_squared() > settings.icons.charge_size.powi(2) {
+            continue;
+        }
+
+        let Some(charge) = charges.charges.iter_mut().find(|c| c.id == charge_icon.id) else {
+            continue;
+        };
+        let fixed = !charge.fixed;
+        charge.set_fixed(fixed);
+        return;
+    }
+}
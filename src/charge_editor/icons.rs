@@ -20,11 +20,11 @@ use crate::{controls::state::ControlState, setting::Settings};
 pub struct Dragging;
 #[derive(Component)]
 pub struct ChargeIcon {
-    pub id: usize,
+    pub id: u32,
 }
 #[derive(Component)]
 pub struct ArrowIcon {
-    id: usize,
+    id: u32,
 }
 
 #[derive(Resource)]
@@ -60,7 +60,7 @@ pub struct IconBuilder {
     anchor: Anchor,
 }
 impl IconBuilders {
-    pub fn build_charge(&self, commands: &mut Commands, pos: Vec2, id: usize) -> (Entity, Entity) {
+    pub fn build_charge(&self, commands: &mut Commands, pos: Vec2, id: u32) -> (Entity, Entity) {
         let mut arrow_ent = Entity::PLACEHOLDER;
         let charge_ent = commands
             .spawn((
@@ -89,7 +89,7 @@ impl IconBuilders {
 
         (charge_ent, arrow_ent)
     }
-    pub fn build_arrow(&self, commands: &mut ChildBuilder, id: usize) -> Entity {
+    pub fn build_arrow(&self, commands: &mut ChildBuilder, id: u32) -> Entity {
         commands
             .spawn((
                 SpriteBundle {
@@ -6,10 +6,12 @@ use bevy::{
 use self::{
     charge_editor::{
         create_charge, edit_charge, edit_velocity, if_create_charge, if_edit_charge,
-        if_edit_velocity, if_move_charge, move_charge, spawn_ui, update_editor_mode, EditorState,
+        if_edit_velocity, if_move_charge, if_pin_charge, move_charge, pin_charge, spawn_ui,
+        update_editor_mode, update_icons, EditorState,
     },
     icons::{drag_icons, setup_builders},
 };
+use crate::system::move_charges;
 
 pub mod charge_editor;
 mod icons;
@@ -22,15 +24,20 @@ impl Plugin for ChargeEditorPlugin {
             .add_systems(Startup, (spawn_ui, setup_builders))
             .add_systems(
                 Update,
+                // Charges can be removed while stepping, so icons catch up before any
+                // tool looks one up
                 (
+                    update_icons,
                     update_editor_mode,
                     create_charge.run_if(if_create_charge),
                     move_charge.run_if(if_move_charge),
                     edit_velocity.run_if(if_edit_velocity),
                     edit_charge.run_if(if_edit_charge),
+                    pin_charge.run_if(if_pin_charge),
                     drag_icons,
                 )
-                    .chain(),
+                    .chain()
+                    .after(move_charges),
             );
     }
 }
@@ -0,0 +1,405 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::Assets,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    hierarchy::BuildChildren,
+    prelude::default,
+    render::{
+        color::Color,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{Image, ImageSampler},
+    },
+    text::{Text, TextStyle},
+    ui::{
+        node_bundles::{ImageBundle, NodeBundle, TextBundle},
+        FlexDirection, JustifyContent, PositionType, Style, UiImage, UiRect, Val,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{background::Background, setting::Settings, vector_field::FieldView};
+
+// Auto ranges ignore the tails so the spikes next to charges don't wash everything out
+const AUTO_PERCENTILES: [f32; 2] = [0.02, 0.98];
+const LEGEND_TEXELS: usize = 256;
+const LEGEND_LABELS: usize = 5;
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x50, 0x64],
+    [0xfb, 0x87, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+const CIVIDIS: [[u8; 3]; 5] = [
+    [0x00, 0x20, 0x4d],
+    [0x41, 0x4d, 0x6b],
+    [0x7c, 0x7b, 0x78],
+    [0xbc, 0xaf, 0x6f],
+    [0xff, 0xea, 0x46],
+];
+const COOL_WARM: [[u8; 3]; 9] = [
+    [0x3b, 0x4c, 0xc0],
+    [0x62, 0x82, 0xea],
+    [0x8d, 0xb0, 0xfe],
+    [0xb8, 0xd0, 0xf9],
+    [0xdd, 0xdd, 0xdd],
+    [0xf5, 0xc4, 0xac],
+    [0xf4, 0x9a, 0x7b],
+    [0xde, 0x60, 0x4d],
+    [0xb4, 0x04, 0x26],
+];
+const BLUE_RED: [[u8; 3]; 9] = [
+    [0x21, 0x66, 0xac],
+    [0x43, 0x93, 0xc3],
+    [0x92, 0xc5, 0xde],
+    [0xd1, 0xe5, 0xf0],
+    [0xf7, 0xf7, 0xf7],
+    [0xfd, 0xdb, 0xc7],
+    [0xf4, 0xa5, 0x82],
+    [0xd6, 0x60, 0x4d],
+    [0xb2, 0x18, 0x2b],
+];
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Palette {
+    Viridis,
+    Magma,
+    Cividis,
+    // Diverging, negative values blue and positive red
+    CoolWarm,
+    BlueRed,
+}
+impl Palette {
+    fn stops(&self) -> &'static [[u8; 3]] {
+        return match self {
+            Palette::Viridis => &VIRIDIS,
+            Palette::Magma => &MAGMA,
+            Palette::Cividis => &CIVIDIS,
+            Palette::CoolWarm => &COOL_WARM,
+            Palette::BlueRed => &BLUE_RED,
+        };
+    }
+
+    // The palette to use when the sign matters, CoolWarm for any that isn't diverging
+    fn diverging(&self) -> Palette {
+        if self.is_diverging() {
+            return *self;
+        }
+        return Palette::CoolWarm;
+    }
+
+    pub fn is_diverging(&self) -> bool {
+        return matches!(self, Palette::CoolWarm | Palette::BlueRed);
+    }
+
+    // t runs from 0 to 1 across the palette
+    pub fn color(&self, t: f32) -> Color {
+        let stops = self.stops();
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+
+        let pos = t * (stops.len() - 1) as f32;
+        let i = (pos as usize).min(stops.len() - 2);
+        let f = pos - i as f32;
+        let channel = |c: usize| {
+            let (a, b) = (stops[i][c] as f32, stops[i + 1][c] as f32);
+            return (a + (b - a) * f) / 255.0;
+        };
+        return Color::rgb(channel(0), channel(1), channel(2));
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Normalisation {
+    Linear,
+    // Values at or below zero take the bottom colour
+    Log,
+    // Linear within linear_width of zero and logarithmic beyond it
+    SymLog { linear_width: f32 },
+}
+impl Normalisation {
+    fn forward(&self, value: f32) -> f32 {
+        return match *self {
+            Normalisation::Linear => value,
+            Normalisation::Log => value.max(f32::MIN_POSITIVE).ln(),
+            Normalisation::SymLog { linear_width } => {
+                value.signum() * (value.abs() / linear_width).ln_1p()
+            }
+        };
+    }
+
+    fn inverse(&self, value: f32) -> f32 {
+        return match *self {
+            Normalisation::Linear => value,
+            Normalisation::Log => value.exp(),
+            Normalisation::SymLog { linear_width } => {
+                value.signum() * value.abs().exp_m1() * linear_width
+            }
+        };
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Range {
+    Auto,
+    Fixed { min: f32, max: f32 },
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct ColorMap {
+    pub palette: Palette,
+    pub normalisation: Normalisation,
+    pub range: Range,
+}
+impl ColorMap {
+    // The same map with a diverging palette centred on zero, so negative and positive values
+    // land on opposite sides of it. Log can't show negatives, so it becomes linear
+    pub fn signed(&self) -> ColorMap {
+        let normalisation = match self.normalisation {
+            Normalisation::Log => Normalisation::Linear,
+            normalisation => normalisation,
+        };
+        return ColorMap {
+            palette: self.palette.diverging(),
+            normalisation,
+            range: self.range,
+        };
+    }
+
+    // The [min, max] values mapped onto the ends of the palette
+    pub fn range(&self, grid: &[Vec<f32>]) -> [f32; 2] {
+        let [mut min, mut max] = match self.range {
+            Range::Fixed { min, max } => [min, max],
+            Range::Auto => {
+                let mut sorted: Vec<f32> = grid
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .filter(|v| v.is_finite())
+                    .collect();
+                if sorted.is_empty() {
+                    return [0.0, 1.0];
+                }
+                sorted.sort_by(|a, b| a.total_cmp(b));
+
+                let at = |p: f32| sorted[(p * (sorted.len() - 1) as f32).round() as usize];
+                [at(AUTO_PERCENTILES[0]), at(AUTO_PERCENTILES[1])]
+            }
+        };
+
+        if matches!(self.range, Range::Auto) && self.palette.is_diverging() {
+            max = min.abs().max(max.abs());
+            min = -max;
+        }
+        if matches!(self.normalisation, Normalisation::Log) && min <= 0.0 {
+            min = max * 1e-3;
+        }
+        return [min, max];
+    }
+
+    // Position of value between the ends of range, from 0 to 1
+    pub fn normalise(&self, value: f32, range: [f32; 2]) -> f32 {
+        let [min, max] = range.map(|v| self.normalisation.forward(v));
+        if max <= min {
+            return 0.5;
+        }
+        return ((self.normalisation.forward(value) - min) / (max - min)).clamp(0.0, 1.0);
+    }
+
+    pub fn denormalise(&self, t: f32, range: [f32; 2]) -> f32 {
+        let [min, max] = range.map(|v| self.normalisation.forward(v));
+        return self.normalisation.inverse(min + (max - min) * t);
+    }
+
+    pub fn color(&self, value: f32, range: [f32; 2]) -> Color {
+        return self.palette.color(self.normalise(value, range));
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum Legend {
+    Hidden,
+    Arrows,
+    Background,
+}
+
+// Ranges last used to colour each layer, so the legend can label them
+#[derive(Resource)]
+pub struct ColorRanges {
+    pub arrows: [f32; 2],
+    pub background: [f32; 2],
+}
+impl Default for ColorRanges {
+    fn default() -> Self {
+        Self {
+            arrows: [0.0, 1.0],
+            background: [0.0, 1.0],
+        }
+    }
+}
+
+// Index from the top of the colour bar
+#[derive(Component)]
+pub struct LegendLabel(usize);
+
+pub struct ColorMapPlugin;
+impl Plugin for ColorMapPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(ColorRanges::default())
+            .add_systems(PostStartup, spawn_legend)
+            .add_systems(Update, update_legend);
+    }
+}
+
+fn legend_map(settings: &Settings) -> Option<(ColorMap, String)> {
+    let simulation = &settings.simulation;
+    let colors = &simulation.colors;
+    return match colors.legend {
+        Legend::Hidden => None,
+        Legend::Arrows => {
+            let title = match simulation.field.view {
+                FieldView::Electric => "|E|",
+                FieldView::Magnetic | FieldView::Both => "B",
+            };
+            Some((
+                simulation.field.view.arrow_colors(&colors.arrows),
+                title.to_string(),
+            ))
+        }
+        Legend::Background => {
+            let title = match simulation.field.background {
+                Background::None => return None,
+                Background::Potential => "Potential",
+                Background::Divergence => "Divergence",
+                Background::Curl => "Curl",
+            };
+            Some((colors.background, title.to_string()))
+        }
+    };
+}
+
+fn spawn_legend(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<Settings>,
+) {
+    let Some((color_map, title)) = legend_map(&settings) else {
+        return;
+    };
+
+    // A single column with the top of the range in the first row
+    let mut data = Vec::with_capacity(LEGEND_TEXELS * 4);
+    for row in 0..LEGEND_TEXELS {
+        let t = 1.0 - row as f32 / (LEGEND_TEXELS - 1) as f32;
+        data.extend_from_slice(&color_map.palette.color(t).as_rgba_u8());
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: 1,
+            height: LEGEND_TEXELS as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::linear();
+    let image = images.add(image);
+
+    let text_style = TextStyle {
+        font_size: 16.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                right: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|p| {
+            p.spawn(TextBundle::from_section(title, text_style.clone()));
+            p.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    height: Val::Px(200.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|p| {
+                p.spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(16.0),
+                        height: Val::Percent(100.0),
+                        margin: UiRect::right(Val::Px(5.0)),
+                        ..default()
+                    },
+                    image: UiImage::new(image),
+                    ..default()
+                });
+                p.spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|p| {
+                    for i in 0..LEGEND_LABELS {
+                        p.spawn((
+                            TextBundle::from_section("", text_style.clone()),
+                            LegendLabel(i),
+                        ));
+                    }
+                });
+            });
+        });
+}
+
+fn update_legend(
+    ranges: Res<ColorRanges>,
+    settings: Res<Settings>,
+    mut labels: Query<(&mut Text, &LegendLabel)>,
+) {
+    if !ranges.is_changed() {
+        return;
+    }
+    let Some((color_map, _)) = legend_map(&settings) else {
+        return;
+    };
+
+    let range = match settings.simulation.colors.legend {
+        Legend::Background => ranges.background,
+        _ => ranges.arrows,
+    };
+    for (mut text, label) in labels.iter_mut() {
+        let t = 1.0 - label.0 as f32 / (LEGEND_LABELS - 1) as f32;
+        text.sections[0].value = format!("{:.3e}", color_map.denormalise(t, range));
+    }
+}
@@ -0,0 +1,82 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::{Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    math::vec3,
+    prelude::default,
+    render::{color::Color, mesh::Mesh},
+    sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
+    transform::components::Transform,
+};
+use vector_fields_core::contours::marching_squares;
+
+use crate::{setting::Settings, utils, vector_field::VectorField};
+
+const CONTOUR_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.6);
+
+#[derive(Resource)]
+pub struct ContourMesh {
+    mesh: Handle<Mesh>,
+}
+
+pub struct ContourPlugin;
+impl Plugin for ContourPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(PostStartup, spawn_contours)
+            .add_systems(Update, update_contours);
+    }
+}
+
+fn spawn_contours(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+) {
+    if !settings.simulation.contours.enabled {
+        return;
+    }
+
+    let mesh = meshes.add(utils::new_line_mesh());
+
+    // Between the background and the arrows
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(mesh.clone()),
+        material: materials.add(ColorMaterial::from(CONTOUR_COLOR)),
+        transform: Transform::from_translation(vec3(0.0, 0.0, -0.5)),
+        ..default()
+    });
+    commands.insert_resource(ContourMesh { mesh });
+}
+
+fn update_contours(
+    mut meshes: ResMut<Assets<Mesh>>,
+    contour_mesh: Option<Res<ContourMesh>>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    let Some(contour_mesh) = contour_mesh else {
+        return;
+    };
+    if !vector_field.is_changed() {
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&contour_mesh.mesh) else {
+        return;
+    };
+
+    let grid = &vector_field.potential;
+    let scale = settings.simulation.scale;
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    for level in settings.simulation.contours.levels.values(grid) {
+        for [a, b] in marching_squares(grid, &vector_field.coords, level) {
+            positions.push((a * scale).extend(0.0).to_array());
+            positions.push((b * scale).extend(0.0).to_array());
+        }
+    }
+    utils::set_line_positions(mesh, positions);
+}
@@ -1,13 +1,15 @@
-use bevy::ecs::system::Resource;
+use bevy::{ecs::system::Resource, input::keyboard::KeyCode};
 
 #[derive(Resource)]
 pub struct Bindings {
     pub double_click_window: f32,
+    pub export_field: KeyCode,
 }
 impl Default for Bindings {
     fn default() -> Self {
         Self {
             double_click_window: 0.2,
+            export_field: KeyCode::F,
         }
     }
 }
@@ -5,7 +5,7 @@ use self::{
     state::{update_control_state, ControlState},
 };
 
-mod bindings;
+pub mod bindings;
 pub mod state;
 
 pub struct ControlPlugin;
@@ -0,0 +1,165 @@
+use bevy::{
+    app::{Plugin, Startup, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    prelude::default,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{node_bundles::TextBundle, PositionType, Style, Val},
+};
+
+use vector_fields_core::{charge::Charges, clock::SimulationClock, diagnostics::Snapshot};
+
+use crate::{
+    history::{self, Recording},
+    setting::Settings,
+    system::move_charges,
+    vector_field::VectorField,
+};
+
+pub struct DiagnosticsPlugin;
+impl Plugin for DiagnosticsPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.insert_resource(Measurements::default())
+            .add_systems(Startup, spawn_hud)
+            .add_systems(
+                Update,
+                // After stepping and the recording have noticed any edits this frame
+                (measure, log_drift, update_hud)
+                    .chain()
+                    .after(move_charges)
+                    .after(history::forget_edits),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Measurements {
+    pub current: Snapshot,
+    pub initial: Option<Snapshot>,
+    num_charges: usize,
+    edits: usize,
+    steps: usize,
+    last_log: f32,
+}
+
+#[derive(Component)]
+pub struct DiagnosticsHud;
+
+fn spawn_hud(mut commands: Commands, settings: Res<Settings>) {
+    if !settings.simulation.diagnostics.hud {
+        return;
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        DiagnosticsHud,
+    ));
+}
+
+fn measure(
+    mut measurements: ResMut<Measurements>,
+    charges: Res<Charges>,
+    vector_field: Res<VectorField>,
+    clock: Res<SimulationClock>,
+    recording: Res<Recording>,
+    settings: Res<Settings>,
+) {
+    if !charges.is_changed() {
+        return;
+    }
+    // Nothing shows the measurements, and the potential energy isn't free
+    let diagnostics = settings.simulation.diagnostics;
+    if !diagnostics.hud && diagnostics.log_interval <= 0.0 {
+        return;
+    }
+
+    let bounds = vector_field.get_bounds();
+    let solver = settings.simulation.solver;
+    let snapshot = Snapshot::measure(&charges, bounds, solver, clock.elapsed);
+
+    // Adding or losing charges, editing them, loading a scene or scrubbing back in time
+    // starts a new baseline
+    let num_charges = charges.charges.len();
+    if measurements.initial.is_none()
+        || measurements.num_charges != num_charges
+        || measurements.edits != recording.edits()
+        || clock.steps < measurements.steps
+    {
+        measurements.initial = Some(snapshot);
+        measurements.num_charges = num_charges;
+        measurements.edits = recording.edits();
+        measurements.last_log = clock.elapsed;
+    }
+    measurements.steps = clock.steps;
+    measurements.current = snapshot;
+}
+
+fn log_drift(mut measurements: ResMut<Measurements>, settings: Res<Settings>) {
+    let log_interval = settings.simulation.diagnostics.log_interval;
+    if log_interval <= 0.0 {
+        return;
+    }
+
+    let current = measurements.current;
+    if current.time - measurements.last_log < log_interval {
+        return;
+    }
+    measurements.last_log = current.time;
+
+    if let Some(initial) = measurements.initial {
+        let (energy, momentum, angular_momentum) = current.drift(&initial);
+        println!(
+            "t={:.2} E={:.5} dE/E0={:.3e} dP=({:.3e}, {:.3e}) dL={:.3e}",
+            current.time,
+            current.total_energy(),
+            energy,
+            momentum.x,
+            momentum.y,
+            angular_momentum
+        );
+    }
+}
+
+fn update_hud(measurements: Res<Measurements>, mut hud: Query<&mut Text, With<DiagnosticsHud>>) {
+    if !measurements.is_changed() {
+        return;
+    }
+
+    let current = measurements.current;
+    for mut text in hud.iter_mut() {
+        let mut value = format!(
+            "t {:.2}\nKE {:.4}  PE {:.4}  E {:.4}\nP ({:.4}, {:.4})  L {:.4}",
+            current.time,
+            current.kinetic,
+            current.potential,
+            current.total_energy(),
+            current.momentum.x,
+            current.momentum.y,
+            current.angular_momentum
+        );
+        if let Some(initial) = measurements.initial {
+            let (energy, _, _) = current.drift(&initial);
+            value += &format!("\ndE/E0 {:.3e}", energy);
+        }
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::math::Vec2;
+use bevy::{
+    app::{Plugin, Update},
+    ecs::system::Res,
+    input::{keyboard::KeyCode, Input},
+};
+use serde::{Deserialize, Serialize};
+use vector_fields_core::{
+    charge::Charge,
+    clock::SimulationClock,
+    field_grid::FieldGrid,
+    grid_export::{self, Metadata},
+    trajectory::{ColumnarWriter, CsvWriter},
+};
+
+use crate::{
+    controls::bindings::Bindings, json_parser::JSONParser, setting::Settings,
+    vector_field::VectorField,
+};
+
+pub struct ExportPlugin;
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(Update, export_field);
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum TrajectoryFormat {
+    Csv,
+    // See vector_fields_core::trajectory for the layout
+    Columnar,
+    Both,
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum FieldFormat {
+    Csv,
+    // One array per quantity plus a json file with the grid's metadata
+    Npy,
+    // Legacy ASCII structured grid, opens in ParaView
+    Vtk,
+    All,
+}
+
+// Streams rows into trajectory.csv and/or trajectory.vft in dir as they're produced
+pub struct TrajectoryWriter {
+    csv: Option<(PathBuf, CsvWriter<BufWriter<File>>)>,
+    columnar: Option<(PathBuf, ColumnarWriter)>,
+    pub rows: usize,
+}
+impl TrajectoryWriter {
+    pub fn create(dir: &Path, format: TrajectoryFormat) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+
+        let mut writer = Self {
+            csv: None,
+            columnar: None,
+            rows: 0,
+        };
+        if matches!(format, TrajectoryFormat::Csv | TrajectoryFormat::Both) {
+            let path = dir.join("trajectory.csv");
+            let csv = CsvWriter::new(BufWriter::new(File::create(&path)?))?;
+            writer.csv = Some((path, csv));
+        }
+        if matches!(format, TrajectoryFormat::Columnar | TrajectoryFormat::Both) {
+            let path = dir.join("trajectory.vft");
+            let columnar = ColumnarWriter::create(&path)?;
+            writer.columnar = Some((path, columnar));
+        }
+        return Ok(writer);
+    }
+
+    // A row for every charge, a holds their accelerations in the same order
+    pub fn write(
+        &mut self,
+        charges: &[Charge],
+        clock: &SimulationClock,
+        a: &[Vec2],
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some((_, csv)) = self.csv.as_mut() {
+            csv.write(charges, clock, a)?;
+        }
+        if let Some((_, columnar)) = self.columnar.as_mut() {
+            columnar.write(charges, clock, a)?;
+        }
+        self.rows += charges.len();
+        Ok(())
+    }
+
+    // Completes every file and returns the paths written
+    pub fn finish(self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut written = vec![];
+        if let Some((path, csv)) = self.csv {
+            csv.finish()?;
+            written.push(path);
+        }
+        if let Some((path, columnar)) = self.columnar {
+            columnar.finish()?;
+            written.push(path);
+        }
+        return Ok(written);
+    }
+}
+
+// Writes the grid into dir in the given formats and returns the paths written
+pub fn write_field(
+    grid: &FieldGrid,
+    dir: &Path,
+    format: FieldFormat,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+
+    let mut written = vec![];
+    if matches!(format, FieldFormat::Csv | FieldFormat::All) {
+        let path = dir.join("field.csv");
+        let mut out = BufWriter::new(File::create(&path)?);
+        grid_export::write_csv(grid, &mut out)?;
+        out.flush()?;
+        written.push(path);
+    }
+    if matches!(format, FieldFormat::Npy | FieldFormat::All) {
+        for (name, values) in grid_export::quantities(grid) {
+            let path = dir.join(format!("field_{}.npy", name));
+            let mut out = BufWriter::new(File::create(&path)?);
+            grid_export::write_npy(&values, &mut out)?;
+            out.flush()?;
+            written.push(path);
+        }
+        let path = dir.join("field.json");
+        JSONParser::save(&path.to_string_lossy(), &Metadata::new(grid))?;
+        written.push(path);
+    }
+    if matches!(format, FieldFormat::Vtk | FieldFormat::All) {
+        let path = dir.join("field.vtk");
+        let mut out = BufWriter::new(File::create(&path)?);
+        grid_export::write_vtk(grid, &mut out)?;
+        out.flush()?;
+        written.push(path);
+    }
+    return Ok(written);
+}
+
+fn export_field(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    if !keys.just_pressed(bindings.export_field) {
+        return;
+    }
+
+    let simulation = &settings.simulation;
+    let dir = Path::new(&simulation.export_dir);
+    match write_field(&vector_field, dir, simulation.field.export_format) {
+        Ok(paths) => {
+            for path in paths {
+                println!("Exported field to {}", path.display());
+            }
+        }
+        Err(e) => println!("Error exporting field {}", e),
+    }
+}
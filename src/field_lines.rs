@@ -0,0 +1,84 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::{Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    math::vec3,
+    prelude::default,
+    render::{color::Color, mesh::Mesh},
+    sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
+    transform::components::Transform,
+};
+use vector_fields_core::{charge::Charges, clock::SimulationClock, field_lines::trace};
+
+use crate::{setting::Settings, utils, vector_field::VectorField};
+
+const FIELD_LINE_COLOR: Color = Color::rgba(1.0, 0.9, 0.4, 0.8);
+
+#[derive(Resource)]
+pub struct FieldLineMesh {
+    mesh: Handle<Mesh>,
+}
+
+pub struct FieldLinePlugin;
+impl Plugin for FieldLinePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(PostStartup, spawn_field_lines)
+            .add_systems(Update, update_field_lines);
+    }
+}
+
+fn spawn_field_lines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+) {
+    if !settings.simulation.field_lines.enabled {
+        return;
+    }
+
+    let mesh = meshes.add(utils::new_line_mesh());
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(mesh.clone()),
+        material: materials.add(ColorMaterial::from(FIELD_LINE_COLOR)),
+        transform: Transform::from_translation(vec3(0.0, 0.0, -0.25)),
+        ..default()
+    });
+    commands.insert_resource(FieldLineMesh { mesh });
+}
+
+fn update_field_lines(
+    mut meshes: ResMut<Assets<Mesh>>,
+    field_line_mesh: Option<Res<FieldLineMesh>>,
+    charges: Res<Charges>,
+    vector_field: Res<VectorField>,
+    clock: Res<SimulationClock>,
+    settings: Res<Settings>,
+) {
+    let Some(field_line_mesh) = field_line_mesh else {
+        return;
+    };
+    if !charges.is_changed() {
+        return;
+    }
+    let Some(mesh) = meshes.get_mut(&field_line_mesh.mesh) else {
+        return;
+    };
+
+    let simulation = &settings.simulation;
+    let bounds = vector_field.get_bounds();
+    let e_field = charges.e_field_sampler(clock.elapsed, bounds, simulation.solver);
+    let polylines = trace(&charges, e_field, bounds, simulation.field_lines);
+
+    let mut positions: Vec<[f32; 3]> = vec![];
+    for line in polylines.iter() {
+        for pair in line.windows(2) {
+            positions.push((pair[0] * simulation.scale).extend(0.0).to_array());
+            positions.push((pair[1] * simulation.scale).extend(0.0).to_array());
+        }
+    }
+    utils::set_line_positions(mesh, positions);
+}
@@ -0,0 +1,156 @@
+use std::{error::Error, path::PathBuf};
+
+use serde::Deserialize;
+use vector_fields_core::{
+    boundary::Boundary, charge::Charges, clock::SimulationClock, diagnostics::Snapshot,
+    field_grid::FieldGrid, integrator::Integrator,
+};
+
+use crate::{
+    export::{write_field, FieldFormat, TrajectoryFormat, TrajectoryWriter},
+    json_parser::JSONParser,
+    setting::Settings,
+};
+
+const USAGE: &str = "usage: headless <save.json> [--steps N | --time T] [--dt DT] \
+[--integrator NAME] [--boundary NAME] [--every K] [--format Csv|Columnar|Both] \
+[--field Csv|Npy|Vtk|All] [--out DIR]";
+
+enum Duration {
+    Steps(usize),
+    Time(f32),
+}
+
+pub struct Run {
+    save: String,
+    duration: Duration,
+    dt: Option<f32>,
+    integrator: Option<Integrator>,
+    boundary: Option<Boundary>,
+    // Trajectory rows are written every this many steps
+    every: usize,
+    format: Option<TrajectoryFormat>,
+    // Writes the final field as well when set
+    field: Option<FieldFormat>,
+    out: PathBuf,
+}
+impl Run {
+    // args are everything after the headless flag
+    pub fn from_args(args: &[String]) -> Result<Self, Box<dyn Error>> {
+        let Some(save) = args.first() else {
+            return Err(USAGE.into());
+        };
+
+        let mut run = Self {
+            save: save.clone(),
+            duration: Duration::Steps(1000),
+            dt: None,
+            integrator: None,
+            boundary: None,
+            every: 1,
+            format: None,
+            field: None,
+            out: PathBuf::from("out"),
+        };
+
+        let mut rest = args[1..].iter();
+        while let Some(flag) = rest.next() {
+            let Some(value) = rest.next() else {
+                return Err(format!("missing value for {}\n{}", flag, USAGE).into());
+            };
+            match flag.as_str() {
+                "--steps" => run.duration = Duration::Steps(value.parse()?),
+                "--time" => run.duration = Duration::Time(value.parse()?),
+                "--dt" => run.dt = Some(value.parse()?),
+                "--integrator" => run.integrator = Some(parse_variant(value)?),
+                "--boundary" => run.boundary = Some(parse_variant(value)?),
+                "--every" => run.every = value.parse::<usize>()?.max(1),
+                "--format" => run.format = Some(parse_variant(value)?),
+                "--field" => run.field = Some(parse_variant(value)?),
+                "--out" => run.out = PathBuf::from(value),
+                _ => return Err(format!("unknown flag {}\n{}", flag, USAGE).into()),
+            }
+        }
+
+        return Ok(run);
+    }
+
+    pub fn run(&self, settings: &Settings) -> Result<(), Box<dyn Error>> {
+        let simulation = &settings.simulation;
+        let mut charges = JSONParser::load::<Charges>(&self.save)?;
+        charges.assign_ids();
+        if let Some(boundary) = self.boundary {
+            charges.boundary = boundary;
+        }
+        let integrator = self.integrator.unwrap_or(simulation.integrator);
+        let dt = self.dt.unwrap_or(simulation.timestep.dt);
+        let steps = match self.duration {
+            Duration::Steps(steps) => steps,
+            Duration::Time(time) => (time / dt).ceil() as usize,
+        };
+
+        let field = &simulation.field;
+        let mut grid = FieldGrid::new(field.size, field.resolution);
+        let bounds = grid.get_bounds();
+
+        let solver = simulation.solver;
+        let format = self.format.unwrap_or(simulation.history.export_format);
+        let mut trajectory = TrajectoryWriter::create(&self.out, format)?;
+        let mut clock = SimulationClock::default();
+        let initial = Snapshot::measure(&charges, bounds, solver, clock.elapsed);
+        for _ in 0..steps {
+            // A timed run shortens its last step so it stops on the requested time
+            let dt = match self.duration {
+                Duration::Steps(_) => dt,
+                Duration::Time(time) => dt.min(time - clock.elapsed),
+            };
+            if dt <= 0.0 {
+                break;
+            }
+
+            // Rows are written once the step has evaluated their accelerations
+            let recorded = (clock.steps % self.every == 0).then(|| charges.charges.clone());
+            let a = charges.step(integrator, clock.elapsed, dt, bounds, solver);
+            if let Some(recorded) = recorded {
+                trajectory.write(&recorded, &clock, &a)?;
+            }
+
+            charges.resolve_encounters();
+            charges.apply_boundary(bounds);
+            clock.tick(dt);
+        }
+        // Always end on the final state, even off the every grid. Nothing steps on from it
+        // to evaluate its accelerations
+        let a = charges.current_accelerations(clock.elapsed, bounds, solver);
+        trajectory.write(&charges.charges, &clock, &a)?;
+        trajectory.finish()?;
+
+        if let Some(field) = self.field {
+            charges.apply_to_field(&mut grid, clock.elapsed, solver);
+            write_field(&grid, &self.out, field)?;
+        }
+
+        let out = self.out.join("final.json");
+        JSONParser::save(&out.to_string_lossy(), &charges)?;
+
+        let last = Snapshot::measure(&charges, bounds, solver, clock.elapsed);
+        let (energy, _, _) = last.drift(&initial);
+        println!(
+            "{} steps, t {:.4}, {} charges, E {:.6} dE/E0 {:.3e}",
+            clock.steps,
+            clock.elapsed,
+            charges.charges.len(),
+            last.total_energy(),
+            energy
+        );
+        Ok(())
+    }
+}
+
+// Accepts bare variant names as well as full JSON for variants with fields
+fn parse_variant<T: for<'a> Deserialize<'a>>(value: &str) -> Result<T, Box<dyn Error>> {
+    if let Ok(parsed) = serde_json::from_str(value) {
+        return Ok(parsed);
+    }
+    return Ok(serde_json::from_str(&format!("\"{}\"", value))?);
+}
@@ -0,0 +1,401 @@
+use std::{collections::VecDeque, error::Error, path::Path};
+
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    ecs::{
+        change_detection::DetectChanges,
+        component::{Component, Tick},
+        query::{Changed, With, Without},
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    hierarchy::{BuildChildren, Children},
+    math::Vec2,
+    prelude::default,
+    render::color::Color,
+    text::{Text, TextStyle},
+    ui::{
+        node_bundles::{ButtonBundle, NodeBundle, TextBundle},
+        AlignItems, Interaction, JustifyContent, PositionType, RelativeCursorPosition, Style,
+        UiRect, Val,
+    },
+};
+use vector_fields_core::{barnes_hut::Solver, charge::Charges, clock::SimulationClock};
+
+use crate::{
+    export::TrajectoryWriter, setting::Settings, system::SystemStatus, vector_field::VectorField,
+};
+
+const TRACK_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const FILL_COLOR: Color = Color::rgb(0.35, 0.75, 0.35);
+
+// The scene after a step, along with the clock that got it there
+#[derive(Clone)]
+pub struct Frame {
+    pub charges: Charges,
+    pub clock: SimulationClock,
+    // Evaluated by the step taken from this frame, None until one has been
+    pub accelerations: Option<Vec<Vec2>>,
+}
+
+// Ring buffer of the last few thousand steps, oldest first
+#[derive(Resource)]
+pub struct Recording {
+    pub frames: VecDeque<Frame>,
+    // Frame being shown while scrubbing, None while following the live simulation
+    pub cursor: Option<usize>,
+    length: usize,
+    // When Charges was last changed by stepping or scrubbing, any later change is an edit
+    seen: Option<Tick>,
+    // How many edits have been seen, so other systems can tell when one happened
+    edits: usize,
+}
+impl Recording {
+    pub fn new(length: usize) -> Self {
+        return Self {
+            frames: VecDeque::with_capacity(length),
+            cursor: None,
+            length,
+            seen: None,
+            edits: 0,
+        };
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        return self.length > 0;
+    }
+
+    pub fn record(&mut self, charges: &Charges, clock: &SimulationClock) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        // Stepping from a past frame replaces everything recorded after it
+        if let Some(cursor) = self.cursor.take() {
+            self.frames.truncate(cursor + 1);
+        }
+
+        let frame = Frame {
+            charges: charges.clone(),
+            clock: *clock,
+            accelerations: None,
+        };
+        match self.frames.back_mut() {
+            // Resuming without stepping overwrites the frame it resumed from
+            Some(last) if last.clock.steps == clock.steps => *last = frame,
+            _ => self.frames.push_back(frame),
+        }
+        while self.frames.len() > self.length {
+            self.frames.pop_front();
+        }
+    }
+
+    // Loading a scene or editing charges starts the recording over, since the frames
+    // before it would jump straight to a different scene
+    pub fn forget_edits(&mut self, changed: Tick) {
+        if self.seen.is_some_and(|seen| seen != changed) {
+            self.frames.clear();
+            self.cursor = None;
+            self.edits += 1;
+        }
+        self.seen = Some(changed);
+    }
+
+    pub fn edits(&self) -> usize {
+        return self.edits;
+    }
+
+    // Marks a change to Charges as made by the recording itself
+    pub fn saw(&mut self, changed: Tick) {
+        self.seen = Some(changed);
+    }
+
+    pub fn seek(&mut self, index: usize) -> Option<&Frame> {
+        let frame = self.frames.get(index)?;
+        self.cursor = Some(index);
+        return Some(frame);
+    }
+
+    // Keeps the accelerations a step started from with the frame it stepped from
+    pub fn attach(&mut self, clock: &SimulationClock, accelerations: Vec<Vec2>) {
+        let index = self.cursor.or(self.frames.len().checked_sub(1));
+        let Some(frame) = index.and_then(|i| self.frames.get_mut(i)) else {
+            return;
+        };
+        if frame.clock.steps == clock.steps && frame.charges.charges.len() == accelerations.len() {
+            frame.accelerations = Some(accelerations);
+        }
+    }
+
+    // Writes every recorded frame, only frames no step has left from yet are evaluated again
+    pub fn write(
+        &self,
+        writer: &mut TrajectoryWriter,
+        bounds: [f32; 4],
+        solver: Solver,
+    ) -> Result<(), Box<dyn Error>> {
+        for frame in self.frames.iter() {
+            let evaluated;
+            let a = match &frame.accelerations {
+                Some(a) => a,
+                None => {
+                    let t = frame.clock.elapsed;
+                    evaluated = frame.charges.current_accelerations(t, bounds, solver);
+                    &evaluated
+                }
+            };
+            writer.write(&frame.charges.charges, &frame.clock, a)?;
+        }
+        Ok(())
+    }
+
+    // Position of the shown frame along the timeline, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let len = self.frames.len();
+        if len < 2 {
+            return 1.0;
+        }
+        let index = self.cursor.unwrap_or(len - 1);
+        return index as f32 / (len - 1) as f32;
+    }
+}
+
+#[derive(Component)]
+pub struct PlayButton;
+
+#[derive(Component)]
+pub struct ExportButton;
+
+#[derive(Component)]
+pub struct TimelineTrack;
+
+#[derive(Component)]
+pub struct TimelineFill;
+
+#[derive(Component)]
+pub struct TimelineLabel;
+
+pub struct HistoryPlugin;
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(PostStartup, spawn_timeline).add_systems(
+            Update,
+            (
+                forget_edits,
+                toggle_playback,
+                scrub,
+                export_history,
+                update_timeline,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn spawn_timeline(mut commands: Commands, recording: Res<Recording>) {
+    let text_style = TextStyle {
+        font_size: 16.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                left: Val::Percent(25.0),
+                width: Val::Percent(50.0),
+                height: Val::Px(24.0),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|p| {
+            p.spawn((button_bundle(), PlayButton)).with_children(|p| {
+                p.spawn(TextBundle::from_section("Play", text_style.clone()));
+            });
+            // Without a recording there's nothing to scrub through or export
+            if !recording.is_enabled() {
+                return;
+            }
+
+            p.spawn((button_bundle(), ExportButton)).with_children(|p| {
+                p.spawn(TextBundle::from_section("Export", text_style.clone()));
+            });
+            p.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_grow: 1.0,
+                        height: Val::Px(8.0),
+                        ..default()
+                    },
+                    background_color: TRACK_COLOR.into(),
+                    ..default()
+                },
+                Interaction::default(),
+                RelativeCursorPosition::default(),
+                TimelineTrack,
+            ))
+            .with_children(|p| {
+                p.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: FILL_COLOR.into(),
+                        ..default()
+                    },
+                    TimelineFill,
+                ));
+            });
+            p.spawn((
+                TextBundle::from_section("", text_style).with_style(Style {
+                    width: Val::Px(140.0),
+                    margin: UiRect::left(Val::Px(5.0)),
+                    ..default()
+                }),
+                TimelineLabel,
+            ));
+        });
+}
+
+fn button_bundle() -> ButtonBundle {
+    return ButtonBundle {
+        style: Style {
+            width: Val::Px(60.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            margin: UiRect::right(Val::Px(5.0)),
+            ..default()
+        },
+        background_color: TRACK_COLOR.into(),
+        ..default()
+    };
+}
+
+pub fn forget_edits(charges: Res<Charges>, mut recording: ResMut<Recording>) {
+    if charges.is_changed() {
+        recording.forget_edits(charges.last_changed());
+    }
+}
+
+fn toggle_playback(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
+    mut status: ResMut<SystemStatus>,
+    mut recording: ResMut<Recording>,
+    charges: Res<Charges>,
+    clock: Res<SimulationClock>,
+) {
+    for interaction in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if status.move_charges {
+            status.move_charges = false;
+        } else {
+            // Resuming from the shown frame drops the frames after it
+            recording.record(&charges, &clock);
+            status.move_charges = true;
+        }
+    }
+}
+
+fn scrub(
+    track: Query<(&Interaction, &RelativeCursorPosition), With<TimelineTrack>>,
+    mut status: ResMut<SystemStatus>,
+    mut recording: ResMut<Recording>,
+    mut charges: ResMut<Charges>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    for (interaction, cursor) in track.iter() {
+        if *interaction != Interaction::Pressed || recording.frames.is_empty() {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+
+        // Dragging past either end of the track sticks to the first or last frame
+        let last = recording.frames.len() - 1;
+        let index = (position.x.clamp(0.0, 1.0) * last as f32).round() as usize;
+        if status.move_charges {
+            status.move_charges = false;
+        }
+        if recording.cursor == Some(index) {
+            continue;
+        }
+        if let Some(frame) = recording.seek(index) {
+            *charges = frame.charges.clone();
+            *clock = frame.clock;
+        }
+        recording.saw(charges.last_changed());
+    }
+}
+
+fn export_history(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<ExportButton>)>,
+    recording: Res<Recording>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    for interaction in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let simulation = &settings.simulation;
+        let dir = Path::new(&simulation.export_dir);
+        let bounds = vector_field.get_bounds();
+        let export = || -> Result<_, Box<dyn Error>> {
+            let mut writer = TrajectoryWriter::create(dir, simulation.history.export_format)?;
+            recording.write(&mut writer, bounds, simulation.solver)?;
+            let rows = writer.rows;
+            return Ok((rows, writer.finish()?));
+        };
+        match export() {
+            Ok((rows, paths)) => {
+                for path in paths {
+                    println!("Exported {} rows to {}", rows, path.display());
+                }
+            }
+            Err(e) => println!("Error exporting history {}", e),
+        }
+    }
+}
+
+fn update_timeline(
+    recording: Res<Recording>,
+    status: Res<SystemStatus>,
+    clock: Res<SimulationClock>,
+    mut fills: Query<&mut Style, With<TimelineFill>>,
+    mut labels: Query<&mut Text, With<TimelineLabel>>,
+    buttons: Query<&Children, With<PlayButton>>,
+    mut texts: Query<&mut Text, Without<TimelineLabel>>,
+) {
+    if !recording.is_changed() && !status.is_changed() {
+        return;
+    }
+
+    for mut style in fills.iter_mut() {
+        style.width = Val::Percent(recording.progress() * 100.0);
+    }
+
+    let len = recording.frames.len();
+    let index = recording.cursor.unwrap_or(len.saturating_sub(1));
+    for mut text in labels.iter_mut() {
+        text.sections[0].value =
+            format!("t {:.2}  {}/{}", clock.elapsed, (index + 1).min(len), len);
+    }
+
+    let value = if status.move_charges { "Pause" } else { "Play" };
+    for children in buttons.iter() {
+        if let Ok(mut text) = texts.get_mut(children[0]) {
+            text.sections[0].value = value.to_string();
+        }
+    }
+}
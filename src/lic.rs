@@ -0,0 +1,163 @@
+use bevy::{
+    app::{Plugin, PostStartup, Update},
+    asset::{Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        system::{Commands, Res, ResMut, Resource},
+    },
+    math::{vec2, vec3, Vec2},
+    prelude::default,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{Image, ImageSampler},
+    },
+    sprite::{Sprite, SpriteBundle},
+    transform::components::Transform,
+};
+use serde::{Deserialize, Serialize};
+use vector_fields_core::{
+    charge::Charges,
+    clock::SimulationClock,
+    lic::{convolve, MIN_TEXELS_PER_THREAD},
+    parallel,
+};
+
+use crate::{colormap::ColorMap, setting::Settings, vector_field::VectorField};
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Lic {
+    pub enabled: bool,
+    pub texels_per_cell: usize,
+    // Streamline length either side of a texel, in texels
+    pub length: f32,
+    pub step: f32,
+    pub seed: u32,
+    // Colours the texture by |E| when set, otherwise it stays grey
+    pub tint: Option<ColorMap>,
+}
+impl Lic {
+    // A setting of 0 would leave no texture at all, so it gets one texel per cell
+    fn texels_per_cell(&self) -> usize {
+        return self.texels_per_cell.max(1);
+    }
+}
+
+#[derive(Resource)]
+pub struct LicImage {
+    image: Handle<Image>,
+}
+
+pub struct LicPlugin;
+impl Plugin for LicPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_systems(PostStartup, spawn_lic)
+            .add_systems(Update, update_lic);
+    }
+}
+
+fn spawn_lic(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    vector_field: Res<VectorField>,
+    settings: Res<Settings>,
+) {
+    let lic = settings.simulation.lic;
+    if !lic.enabled {
+        return;
+    }
+
+    let [width, height] = vector_field.get_shape();
+    let [tex_width, tex_height] = [width, height].map(|n| n * lic.texels_per_cell());
+    let mut image = Image::new(
+        Extent3d {
+            width: tex_width as u32,
+            height: tex_height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        vec![0; tex_width * tex_height * 4],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::linear();
+    let image = images.add(image);
+
+    // Covers the same area as the background, above it and below the contours
+    let cell = settings.simulation.scale / vector_field.get_resolution() as f32;
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(vec2(width as f32, height as f32) * cell),
+            ..default()
+        },
+        texture: image.clone(),
+        transform: Transform::from_translation(vec3(0.0, 0.0, -0.9)),
+        ..default()
+    });
+    commands.insert_resource(LicImage { image });
+}
+
+fn update_lic(
+    mut images: ResMut<Assets<Image>>,
+    lic_image: Option<Res<LicImage>>,
+    charges: Res<Charges>,
+    vector_field: Res<VectorField>,
+    clock: Res<SimulationClock>,
+    settings: Res<Settings>,
+) {
+    let Some(lic_image) = lic_image else {
+        return;
+    };
+    if !charges.is_changed() {
+        return;
+    }
+    let Some(image) = images.get_mut(&lic_image.image) else {
+        return;
+    };
+
+    let simulation = &settings.simulation;
+    let lic = simulation.lic;
+    let threads = simulation.solver.threads;
+
+    // Texel centres, starting half a grid cell below and left of the first arrow
+    let [width, height] = vector_field.get_shape();
+    let [tex_width, tex_height] = [width, height].map(|n| n * lic.texels_per_cell());
+    let [min_x, _, min_y, _] = vector_field.get_bounds();
+    let cell = 1.0 / vector_field.get_resolution() as f32;
+    let texel = cell / lic.texels_per_cell() as f32;
+    let origin = vec2(min_x, min_y) - Vec2::splat(0.5 * cell) + Vec2::splat(0.5 * texel);
+
+    let bounds = vector_field.get_bounds();
+    let e_field = charges.e_field_sampler(clock.elapsed, bounds, simulation.solver);
+    let mut e = vec![Vec2::ZERO; tex_width * tex_height];
+    parallel::for_each_chunk(&mut e, threads, MIN_TEXELS_PER_THREAD, |start, chunk| {
+        for (i, value) in chunk.iter_mut().enumerate() {
+            let (x, y) = ((start + i) % tex_width, (start + i) / tex_width);
+            *value = e_field(origin + vec2(x as f32, y as f32) * texel);
+        }
+    });
+    let e: Vec<Vec<Vec2>> = e.chunks(tex_width).map(|row| row.to_vec()).collect();
+
+    let intensity = convolve(&e, lic.length, lic.step, lic.seed, threads);
+    let mags: Vec<Vec<f32>> = e
+        .iter()
+        .map(|row| row.iter().map(|v| v.length()).collect())
+        .collect();
+    let range = lic.tint.map(|tint| tint.range(&mags));
+
+    // Texture rows run top to bottom while the grid runs bottom to top
+    for y in 0..tex_height {
+        let row = tex_height - 1 - y;
+        for x in 0..tex_width {
+            let i = (row * tex_width + x) * 4;
+            let v = intensity[y][x];
+            let rgb = match (lic.tint, range) {
+                (Some(tint), Some(range)) => {
+                    let color = tint.color(mags[y][x], range).as_rgba_f32();
+                    [color[0] * v, color[1] * v, color[2] * v]
+                }
+                _ => [v; 3],
+            };
+            image.data[i..i + 3].copy_from_slice(&rgb.map(|c| (c * 255.0) as u8));
+            image.data[i + 3] = 255;
+        }
+    }
+}
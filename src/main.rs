@@ -1,5 +1,7 @@
 use std::{error::Error, time::Duration};
 
+use arrows::{ArrowPlugin, ArrowRenderer};
+use background::BackgroundPlugin;
 use bevy::{
     app::{App, PluginGroup, Startup, Update},
     asset::AssetServer,
@@ -11,33 +13,58 @@ use bevy::{
     DefaultPlugins,
 };
 use cam::CamPlugin;
-use charge::Charges;
 use charge_editor::{ui_elements::UIPlugin, ChargeEditorPlugin};
+use colormap::ColorMapPlugin;
+use contours::ContourPlugin;
 use controls::ControlPlugin;
+use diagnostics::DiagnosticsPlugin;
+use export::ExportPlugin;
+use field_lines::FieldLinePlugin;
+use headless::Run;
+use history::{HistoryPlugin, Recording};
 use json_parser::JSONParser;
+use lic::LicPlugin;
 use setting::Settings;
 use system::{electric_field_system, SystemStatus};
 use vector_field::VectorField;
+use vector_fields_core::{charge::Charges, clock::SimulationClock};
 
+mod arrows;
+mod background;
 mod cam;
-mod charge;
 mod charge_editor;
+mod colormap;
+mod contours;
 mod controls;
+mod diagnostics;
+mod export;
+mod field_lines;
+mod headless;
+mod history;
 mod initializer;
 mod json_parser;
+mod lic;
 mod setting;
 mod system;
 mod utils;
 mod vector_field;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut app = App::new();
-
     let settings = Settings::load()?;
+
+    // `headless <save.json> ...` steps the save without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|a| a.as_str()) == Some("headless") {
+        return Run::from_args(&args[1..])?.run(&settings);
+    }
+
+    let mut app = App::new();
     let resolution = settings.display.clone().as_resolution();
     let vf_size = settings.simulation.field.size;
     let vf_res = settings.simulation.field.resolution;
-    let charges = JSONParser::load::<Charges>("assets/saves/empty.json")?;
+    let history_length = settings.simulation.history.length;
+    let mut charges = JSONParser::load::<Charges>("assets/saves/empty.json")?;
+    charges.assign_ids();
     electric_field_system(&mut app);
 
     app.insert_resource(Msaa::Sample4)
@@ -46,6 +73,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         // .insert_resource(CurCharge::default())
         .insert_resource(charges)
         .insert_resource(SystemStatus::default())
+        .insert_resource(SimulationClock::default())
+        .insert_resource(Recording::new(history_length))
         .add_systems(Startup, init_vector_field)
         // .add_systems(Update, change_charge_list)
         .add_plugins((
@@ -70,6 +99,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             ChargeEditorPlugin,
             UIPlugin,
             ControlPlugin,
+            DiagnosticsPlugin,
+            BackgroundPlugin,
+            ContourPlugin,
+            FieldLinePlugin,
+            ArrowPlugin,
+            ColorMapPlugin,
+            LicPlugin,
+            HistoryPlugin,
+            ExportPlugin,
         ));
 
     app.run();
@@ -91,6 +129,7 @@ fn init_vector_field(
         arrow_texture,
         settings.simulation.scale,
         vector.size,
+        matches!(vector.renderer, ArrowRenderer::Sprites),
     );
 }
 
@@ -110,6 +149,7 @@ fn change_charge_list(
         cur_charge.t = Timer::new(Duration::from_secs(20), TimerMode::Once);
         let path = format!("assets/saves/{}.json", cur_charge.i);
         charges.charges = JSONParser::load::<Charges>(&path).unwrap().charges;
+        charges.assign_ids();
         cur_charge.i += 1;
         if cur_charge.i == 6 {
             cur_charge.i = 0
@@ -2,8 +2,20 @@ use std::error::Error;
 
 use bevy::{ecs::system::Resource, math::Vec2};
 use serde::{Deserialize, Serialize};
+use vector_fields_core::{
+    barnes_hut::Solver, clock::Timestep, contours::Levels, field_grid::Stencil,
+    field_lines::FieldLines, integrator::Integrator,
+};
 
-use crate::json_parser::JSONParser;
+use crate::{
+    arrows::ArrowRenderer,
+    background::Background,
+    colormap::{ColorMap, Legend},
+    export::{FieldFormat, TrajectoryFormat},
+    json_parser::JSONParser,
+    lic::Lic,
+    vector_field::{Encoding, FieldView},
+};
 
 #[derive(Resource, Clone, Deserialize, Serialize)]
 pub struct Settings {
@@ -41,20 +53,63 @@ impl Display {
 pub struct Simulation {
     pub time_scale: f32,
     pub scale: f32,
+    pub integrator: Integrator,
+    pub timestep: Timestep,
+    pub solver: Solver,
+    pub diagnostics: Diagnostics,
+    pub history: History,
     pub field: Field,
+    pub contours: Contours,
+    pub field_lines: FieldLines,
+    pub lic: Lic,
     pub vector: Vector,
+    pub colors: Colors,
+    pub export_dir: String,
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Diagnostics {
+    pub hud: bool,
+    // Simulated seconds between drift logs, 0 turns logging off
+    pub log_interval: f32,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct History {
+    // Steps kept for the timeline, 0 turns recording off
+    pub length: usize,
+    pub export_format: TrajectoryFormat,
 }
 
 #[derive(Copy, Clone, Deserialize, Serialize)]
 pub struct Field {
     pub size: [usize; 2],
     pub resolution: usize,
+    pub view: FieldView,
+    pub background: Background,
+    pub stencil: Stencil,
+    pub export_format: FieldFormat,
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Contours {
+    pub enabled: bool,
+    pub levels: Levels,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Vector {
     pub texture: String,
     pub size: f32,
+    pub renderer: ArrowRenderer,
+    pub encoding: Encoding,
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Colors {
+    pub arrows: ColorMap,
+    pub background: ColorMap,
+    pub legend: Legend,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
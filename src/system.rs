@@ -3,19 +3,25 @@ use std::vec;
 use bevy::{
     app::{App, Update},
     ecs::{
+        change_detection::DetectChanges,
         schedule::{IntoSystemConfigs, NodeConfigs},
         system::{Query, Res, ResMut, Resource, System},
     },
     sprite::Sprite,
+    time::Time,
     transform::components::Transform,
 };
+use vector_fields_core::{charge::Charges, clock::SimulationClock};
 
-use crate::{charge::Charges, setting::Settings, vector_field::VectorField};
+use crate::{
+    arrows::ArrowRenderer, colormap::ColorRanges, history::Recording, setting::Settings,
+    vector_field::VectorField,
+};
 
 #[derive(Resource)]
 pub struct SystemStatus {
-    update_field: bool,
-    move_charges: bool,
+    pub update_field: bool,
+    pub move_charges: bool,
 }
 impl Default for SystemStatus {
     fn default() -> Self {
@@ -43,14 +49,34 @@ fn if_update_field(
 ) -> bool {
     return status.update_field && vector_field.is_some() && charges.is_some();
 }
-fn update_field(mut vector_field: ResMut<VectorField>, charges: Res<Charges>) {
-    charges.apply_to_field(&mut vector_field);
+fn update_field(
+    mut vector_field: ResMut<VectorField>,
+    charges: Res<Charges>,
+    clock: Res<SimulationClock>,
+    settings: Res<Settings>,
+) {
+    let solver = settings.simulation.solver;
+    charges.apply_to_field(&mut vector_field, clock.elapsed, solver);
+    vector_field.compute_derivatives(settings.simulation.field.stencil);
 }
 fn update_arrows(
     vector_field: Res<VectorField>,
     mut sprite_query: Query<(&mut Sprite, &mut Transform)>,
+    mut color_ranges: ResMut<ColorRanges>,
+    settings: Res<Settings>,
 ) {
-    if let Err(e) = vector_field.update_sprites(&mut sprite_query) {
+    if !matches!(settings.simulation.vector.renderer, ArrowRenderer::Sprites) {
+        return;
+    }
+
+    let simulation = &settings.simulation;
+    let view = simulation.field.view;
+    let colors = &view.arrow_colors(&simulation.colors.arrows);
+    let range = colors.range(&vector_field.color_values(view));
+    color_ranges.arrows = range;
+
+    let encoding = simulation.vector.encoding;
+    if let Err(e) = vector_field.update_sprites(&mut sprite_query, view, encoding, colors, range) {
         print!("Error updating vector field sprites {}", e);
     }
 }
@@ -58,16 +84,34 @@ fn update_arrows(
 fn if_move_charges(status: Res<SystemStatus>, charges: Option<Res<Charges>>) -> bool {
     return status.move_charges && charges.is_some();
 }
-fn move_charges(
+pub fn move_charges(
     mut charges: ResMut<Charges>,
+    mut clock: ResMut<SimulationClock>,
+    mut recording: ResMut<Recording>,
     vector_field: Res<VectorField>,
     settings: Res<Settings>,
+    time: Res<Time>,
 ) {
-    let time_scale = settings.simulation.time_scale;
+    let simulation = &settings.simulation;
+    let timestep = simulation.timestep;
+    let substeps = clock.advance(time.delta_seconds(), simulation.time_scale, timestep);
 
-    let [width, height] = vector_field.get_shape();
-    let bl = vector_field.coords[0][0];
-    let tr = vector_field.coords[height - 1][width - 1];
-    charges.update_velocities(time_scale);
-    charges.move_charges(time_scale, [bl.x, tr.x, bl.y, tr.y]);
+    recording.forget_edits(charges.last_changed());
+
+    let bounds = vector_field.get_bounds();
+    for _ in 0..substeps {
+        let accelerations = charges.step(
+            simulation.integrator,
+            clock.elapsed,
+            timestep.dt,
+            bounds,
+            simulation.solver,
+        );
+        recording.attach(&clock, accelerations);
+        charges.resolve_encounters();
+        charges.apply_boundary(bounds);
+        clock.tick(timestep.dt);
+        recording.record(&charges, &clock);
+    }
+    recording.saw(charges.last_changed());
 }
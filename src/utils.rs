@@ -1,6 +1,6 @@
 use bevy::{
     math::{Quat, Vec2},
-    render::color::Color,
+    render::{mesh::Mesh, render_resource::PrimitiveTopology},
 };
 
 pub fn dir_to_quat(dir: Vec2) -> Quat {
@@ -9,6 +9,17 @@ pub fn dir_to_quat(dir: Vec2) -> Quat {
     return Quat::from_rotation_z(f32::atan2(dir.y, dir.x));
 }
 
-pub fn mag_to_color(mag: f32) -> Color {
-    return Color::hsla((f32::tanh(mag) * 180.0) + 180.0, 0.5, 0.5, 1.0);
+pub fn new_line_mesh() -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    set_line_positions(&mut mesh, vec![]);
+    return mesh;
+}
+
+// Positions are consumed in pairs, one pair per segment
+pub fn set_line_positions(mesh: &mut Mesh, mut positions: Vec<[f32; 3]>) {
+    // Keep a degenerate segment around so the mesh is never empty
+    if positions.is_empty() {
+        positions = vec![[0.0; 3]; 2];
+    }
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
 }
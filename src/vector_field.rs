@@ -1,4 +1,6 @@
-use crate::utils;
+use std::ops::{Deref, DerefMut};
+
+use crate::{colormap::ColorMap, utils};
 use bevy::{
     asset::Handle,
     ecs::{
@@ -6,41 +8,111 @@ use bevy::{
         query::QueryEntityError,
         system::{Commands, Query, Resource},
     },
-    math::{vec2, vec3, Vec2},
+    math::{vec2, Quat, Vec2, Vec3},
     prelude::default,
     render::{color::Color, texture::Image},
     sprite::{Sprite, SpriteBundle},
     transform::components::Transform,
 };
+use serde::{Deserialize, Serialize};
+use vector_fields_core::field_grid::FieldGrid;
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum FieldView {
+    Electric,
+    Magnetic,
+    Both,
+}
+impl FieldView {
+    // B points into or out of the screen, which only its sign tells apart, so views coloured
+    // by B use a diverging palette with positive (out of the screen) and negative (into it)
+    // on opposite sides
+    pub fn arrow_colors(&self, colors: &ColorMap) -> ColorMap {
+        return match self {
+            FieldView::Electric => *colors,
+            FieldView::Magnetic | FieldView::Both => colors.signed(),
+        };
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub enum LengthScale {
+    Constant,
+    Linear,
+    Log,
+    Sqrt,
+    Clamped { min: f32, max: f32 },
+}
+impl LengthScale {
+    // Relative magnitude in, arrow scale out, a relative magnitude of 1 is a full size arrow
+    pub fn scale(&self, relative: f32) -> f32 {
+        return match *self {
+            LengthScale::Constant => 1.0,
+            LengthScale::Linear => relative,
+            LengthScale::Log => relative.ln_1p() / std::f32::consts::LN_2,
+            LengthScale::Sqrt => relative.sqrt(),
+            LengthScale::Clamped { min, max } => relative.clamp(min, max),
+        };
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Encoding {
+    pub length: LengthScale,
+    // Magnitude drawn as a full size arrow
+    pub reference: f32,
+    // Arrows fade out towards zero below this magnitude, 0 keeps them opaque
+    pub fade_below: f32,
+    // Arrows below this magnitude are not drawn at all
+    pub hide_below: f32,
+}
+impl Encoding {
+    // Scale and opacity of an arrow for a field of the given magnitude
+    pub fn apply(&self, mag: f32) -> (f32, f32) {
+        if !mag.is_finite() || mag < self.hide_below {
+            return (0.0, 0.0);
+        }
+
+        let scale = self.length.scale(mag / self.reference);
+        let alpha = if self.fade_below > 0.0 {
+            (mag / self.fade_below).min(1.0)
+        } else {
+            1.0
+        };
+        return (scale, alpha);
+    }
+}
 
+pub struct Arrow {
+    pub rotation: Quat,
+    pub scale: f32,
+    pub color: Color,
+}
+
+// The grid plus the sprites drawn for it, derefs to the grid so systems can read it directly
 #[derive(Resource)]
 pub struct VectorField {
-    size: [usize; 2],
-    resolution: usize,
-    pub field: Vec<Vec<Vec2>>,
-    pub coords: Vec<Vec<Vec2>>,
+    grid: FieldGrid,
     pub sprites: Vec<Vec<Option<Entity>>>,
 }
+impl Deref for VectorField {
+    type Target = FieldGrid;
+
+    fn deref(&self) -> &FieldGrid {
+        return &self.grid;
+    }
+}
+impl DerefMut for VectorField {
+    fn deref_mut(&mut self) -> &mut FieldGrid {
+        return &mut self.grid;
+    }
+}
 
 impl VectorField {
     pub fn new(size: [usize; 2], resolution: usize) -> Self {
-        let [width, height] = [size[0] * resolution, size[1] * resolution];
-        let mut field = Vec::with_capacity(height);
-        let mut sprites = Vec::with_capacity(height);
-        let mut coords = Vec::with_capacity(height);
-
-        for _ in 0..height {
-            field.push(Vec::with_capacity(width));
-            sprites.push(Vec::with_capacity(width));
-            coords.push(Vec::with_capacity(width));
-        }
-
         return Self {
-            size,
-            resolution,
-            field,
-            coords,
-            sprites,
+            grid: FieldGrid::new(size, resolution),
+            sprites: vec![],
         };
     }
 
@@ -50,52 +122,41 @@ impl VectorField {
         arrow_texture: Handle<Image>,
         spacing: f32,
         arrow_size: f32,
+        spawn_sprites: bool,
     ) {
         let [width, height] = self.get_shape();
 
         for y in 0..height {
-            self.field.push(Vec::with_capacity(width));
             self.sprites.push(Vec::with_capacity(width));
 
             for x in 0..width {
-                let coord = vec3(
-                    (x as f32) - (width as f32 / 2.0),
-                    (y as f32) - (height as f32 / 2.0),
-                    0.0,
-                ) / self.resolution as f32;
-
-                let sprite_ent = commands
-                    .spawn(SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::Hsla {
-                                hue: 0.5,
-                                saturation: 1.0,
-                                lightness: 1.0,
-                                alpha: 1.0,
+                let coord = self.coords[y][x].extend(0.0);
+
+                let sprite_ent = spawn_sprites.then(|| {
+                    commands
+                        .spawn(SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::Hsla {
+                                    hue: 0.5,
+                                    saturation: 1.0,
+                                    lightness: 1.0,
+                                    alpha: 1.0,
+                                },
+                                custom_size: Some(vec2(arrow_size, arrow_size)),
+                                ..default()
                             },
-                            custom_size: Some(vec2(arrow_size, arrow_size)),
+                            texture: arrow_texture.clone(),
+                            transform: Transform::from_translation(coord * spacing),
                             ..default()
-                        },
-                        texture: arrow_texture.clone(),
-                        transform: Transform::from_translation(coord * spacing),
-                        ..default()
-                    })
-                    .id();
-
-                self.sprites[y].push(Some(sprite_ent));
-                self.coords[y].push(coord.truncate());
-                self.field[y].push(vec2(0.0, 1.0));
+                        })
+                        .id()
+                });
+
+                self.sprites[y].push(sprite_ent);
             }
         }
     }
 
-    pub fn get_shape(&self) -> [usize; 2] {
-        return [
-            self.size[0] * self.resolution,
-            self.size[1] * self.resolution,
-        ];
-    }
-
     fn normalise(&self) -> (Vec<Vec<Vec2>>, Vec<Vec<f32>>) {
         let [width, height] = self.get_shape();
 
@@ -118,21 +179,75 @@ impl VectorField {
         return (norm_field, mags);
     }
 
-    pub fn update_sprites(
+    // What the arrows are coloured by in each view
+    pub fn color_values(&self, view: FieldView) -> Vec<Vec<f32>> {
+        return match view {
+            FieldView::Electric => self
+                .field
+                .iter()
+                .map(|row| row.iter().map(|e| e.length()).collect())
+                .collect(),
+            FieldView::Magnetic | FieldView::Both => self.magnetic.clone(),
+        };
+    }
+
+    pub fn arrows(
         &self,
-        sprite_query: &mut Query<(&mut Sprite, &mut Transform)>,
-    ) -> Result<(), QueryEntityError> {
+        view: FieldView,
+        encoding: Encoding,
+        colors: &ColorMap,
+        range: [f32; 2],
+    ) -> Vec<Vec<Arrow>> {
         let [width, height] = self.get_shape();
 
-        let (dir, mag) = self.normalise();
+        let (dir, mags) = self.normalise();
+        let values = self.color_values(view);
+        let mut arrows = Vec::with_capacity(height);
         for y in 0..height {
+            let mut row = Vec::with_capacity(width);
             for x in 0..width {
-                if let Some(sprite_ent) = self.sprites[y][x] {
-                    let (mut s, mut t) = sprite_query.get_mut(sprite_ent)?;
+                let b = self.magnetic[y][x];
+                let mag = match view {
+                    FieldView::Magnetic => b.abs(),
+                    FieldView::Electric | FieldView::Both => mags[y][x],
+                };
+                let (scale, alpha) = encoding.apply(mag);
+
+                let rotation = match view {
+                    FieldView::Electric | FieldView::Both => utils::dir_to_quat(dir[y][x]),
+                    // B has no direction in the plane, its sign is shown by the colour
+                    FieldView::Magnetic => Quat::IDENTITY,
+                };
+                let color = colors.color(values[y][x], range);
+                row.push(Arrow {
+                    rotation,
+                    scale,
+                    color: color.with_a(alpha),
+                });
+            }
+            arrows.push(row);
+        }
+
+        return arrows;
+    }
+
+    pub fn update_sprites(
+        &self,
+        sprite_query: &mut Query<(&mut Sprite, &mut Transform)>,
+        view: FieldView,
+        encoding: Encoding,
+        colors: &ColorMap,
+        range: [f32; 2],
+    ) -> Result<(), QueryEntityError> {
+        let arrows = self.arrows(view, encoding, colors, range);
+        let cells = self.sprites.iter().flatten().zip(arrows.iter().flatten());
+        for (sprite_ent, arrow) in cells {
+            if let Some(sprite_ent) = sprite_ent {
+                let (mut s, mut t) = sprite_query.get_mut(*sprite_ent)?;
 
-                    t.rotation = utils::dir_to_quat(dir[y][x]);
-                    s.color = utils::mag_to_color(mag[y][x]);
-                }
+                t.rotation = arrow.rotation;
+                t.scale = Vec3::splat(arrow.scale);
+                s.color = arrow.color;
             }
         }
 